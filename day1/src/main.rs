@@ -1,42 +1,69 @@
+use num_traits::{PrimInt, Unsigned};
 use simple_error::SimpleError;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt::Display;
+use std::hash::Hash;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
-fn get_input() -> Result<Vec<u32>, Box<dyn Error>> {
+trait Number: PrimInt + Unsigned + Hash + Display + FromStr {}
+
+impl<T: PrimInt + Unsigned + Hash + Display + FromStr> Number for T {}
+
+fn get_input<T: Number>() -> Result<Vec<T>, Box<dyn Error>>
+where
+    T::Err: Error + 'static,
+{
     BufReader::new(std::fs::File::open("input.txt")?)
         .lines()
-        .map(|l| Ok(l?.parse::<u32>()?))
+        .map(|l| Ok(l?.parse::<T>()?))
         .collect::<Result<Vec<_>, Box<dyn Error>>>()
-        .into()
 }
 
-fn part1() -> Result<u32, Box<dyn Error>> {
-    let numbers = get_input()?;
+fn checked_product<T: Number>(numbers: &[T]) -> Result<T, SimpleError> {
+    numbers
+        .iter()
+        .try_fold(T::one(), |acc, n| acc.checked_mul(n))
+        .map(Ok)
+        .unwrap_or_else(|| {
+            Err(SimpleError::new(format!(
+                "product of {} overflows a {}-bit integer",
+                numbers
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" * "),
+                T::zero().count_zeros()
+            )))
+        })
+}
+
+fn part1<T: Number>(numbers: &[T], target: T) -> Result<T, Box<dyn Error>> {
     let mut c = HashSet::new();
-    for n in numbers {
-        if n > 2020 {
+    for n in numbers.iter().copied() {
+        if n > target {
             continue;
         }
         if c.contains(&n) {
-            return Ok(n * (2020 - n));
+            return Ok(checked_product(&[n, target - n])?);
         }
-        c.insert(2020 - n);
+        c.insert(target - n);
     }
 
     Err(SimpleError::new("unable to find answer to part 1").into())
 }
 
-fn part2() -> Result<u32, Box<dyn Error>> {
-    let mut numbers = get_input()?;
+fn part2<T: Number>(numbers: &[T], target: T) -> Result<T, Box<dyn Error>> {
+    let mut numbers = numbers.to_vec();
     numbers.sort();
 
     let relevant_numbers = numbers
         .into_iter()
-        .take_while(|n| *n < 2020)
+        .take_while(|n| *n < target)
         .collect::<Vec<_>>();
 
-    fn le_slice(numbers: &[u32], le: u32) -> &[u32] {
+    fn le_slice<T: Number>(numbers: &[T], le: T) -> &[T] {
         match numbers.binary_search(&le) {
             Ok(i) => {
                 let n_eq = numbers[i..]
@@ -51,11 +78,11 @@ fn part2() -> Result<u32, Box<dyn Error>> {
     }
 
     for n1 in relevant_numbers.iter().copied() {
-        let relevant_slice = le_slice(relevant_numbers.as_slice(), 2020 - n1);
-        for (i, n2) in relevant_slice.into_iter().copied().enumerate() {
-            let n3 = 2020 - n1 - n2;
+        let relevant_slice = le_slice(relevant_numbers.as_slice(), target - n1);
+        for (i, n2) in relevant_slice.iter().copied().enumerate() {
+            let n3 = target - n1 - n2;
             if relevant_slice[(i + 1)..].binary_search(&n3).is_ok() {
-                return Ok(n1 * n2 * n3);
+                return Ok(checked_product(&[n1, n2, n3])?);
             }
         }
     }
@@ -63,8 +90,107 @@ fn part2() -> Result<u32, Box<dyn Error>> {
     Err(SimpleError::new("unable to find answer to part 2").into())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("part 1: {}", part1()?);
-    println!("part 2: {}", part2()?);
+fn all_pairs<T: Number>(numbers: &[T], target: T) -> Vec<(usize, usize)> {
+    let mut seen = HashMap::<T, Vec<usize>>::new();
+    let mut pairs = Vec::new();
+    for (j, n) in numbers.iter().copied().enumerate() {
+        if let Some(indices) = target.checked_sub(&n).and_then(|rest| seen.get(&rest)) {
+            pairs.extend(indices.iter().map(|i| (*i, j)));
+        }
+        seen.entry(n).or_default().push(j);
+    }
+    pairs
+}
+
+fn all_triples<T: Number>(numbers: &[T], target: T) -> Vec<(usize, usize, usize)> {
+    let mut seen = HashMap::<T, Vec<usize>>::new();
+    let mut triples = Vec::new();
+    for (j, n2) in numbers.iter().copied().enumerate() {
+        for (k, n3) in numbers.iter().copied().enumerate().skip(j + 1) {
+            let rest = target.checked_sub(&n2).and_then(|r| r.checked_sub(&n3));
+            if let Some(indices) = rest.and_then(|rest| seen.get(&rest)) {
+                triples.extend(indices.iter().map(|i| (*i, j, k)));
+            }
+        }
+        seen.entry(n2).or_default().push(j);
+    }
+    triples.sort_unstable();
+    triples
+}
+
+fn print_all_solutions<T: Number>(numbers: &[T], target: T) -> Result<(), Box<dyn Error>> {
+    let pairs = all_pairs(numbers, target);
+    for (i, j) in pairs.iter().copied() {
+        println!(
+            "pair: [{}] {} + [{}] {} = {}, product {}",
+            i,
+            numbers[i],
+            j,
+            numbers[j],
+            target,
+            checked_product(&[numbers[i], numbers[j]])?
+        );
+    }
+    println!("pairs found: {}", pairs.len());
+
+    let triples = all_triples(numbers, target);
+    for (i, j, k) in triples.iter().copied() {
+        println!(
+            "triple: [{}] {} + [{}] {} + [{}] {} = {}, product {}",
+            i,
+            numbers[i],
+            j,
+            numbers[j],
+            k,
+            numbers[k],
+            target,
+            checked_product(&[numbers[i], numbers[j], numbers[k]])?
+        );
+    }
+    println!("triples found: {}", triples.len());
+
+    if pairs.len() > 1 || triples.len() > 1 {
+        println!("input is ambiguous");
+    }
+
+    Ok(())
+}
+
+fn run<T: Number>(target: &str, all: bool) -> Result<(), Box<dyn Error>>
+where
+    T::Err: Error + 'static,
+{
+    let target = target.parse::<T>()?;
+    let numbers = get_input::<T>()?;
+    if all {
+        return print_all_solutions(numbers.as_slice(), target);
+    }
+
+    println!("part 1: {}", part1(numbers.as_slice(), target)?);
+    println!("part 2: {}", part2(numbers.as_slice(), target)?);
     Ok(())
 }
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let option = |name: &str, default: &'static str| -> Result<String, SimpleError> {
+        match args.iter().position(|a| a == name) {
+            Some(i) => args
+                .get(i + 1)
+                .cloned()
+                .ok_or_else(|| SimpleError::new(format!("{} requires a value", name))),
+            None => Ok(String::from(default)),
+        }
+    };
+    let all = args.iter().any(|a| a == "--all");
+    let target = option("--target", "2020")?;
+
+    match option("--width", "64")?.as_str() {
+        "32" => run::<u32>(target.as_str(), all),
+        "64" => run::<u64>(target.as_str(), all),
+        "128" => run::<u128>(target.as_str(), all),
+        w => {
+            Err(SimpleError::new(format!("unsupported width {}, expected 32, 64 or 128", w)).into())
+        }
+    }
+}
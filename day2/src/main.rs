@@ -1,104 +1,415 @@
 use itertools::Itertools;
+use regex::bytes::Regex;
+use serde_json::json;
 use simple_error::SimpleError;
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::io::{BufRead, BufReader};
 
+#[derive(Copy, Clone)]
 struct Rule {
     start: u8,
     end: u8,
     character: u8,
 }
 
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{} {}", self.start, self.end, self.character as char)
+    }
+}
+
 struct Password(Vec<u8>);
 
+impl Display for Password {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.0.as_slice()))
+    }
+}
+
 struct Statement {
     rule: Rule,
     password: Password,
 }
 
 trait StatementExt {
-    fn parse(&self) -> Result<Statement, Box<dyn Error>>;
+    fn parse(&self) -> Result<Statement, SimpleError>;
 }
 
 impl<T: AsRef<str>> StatementExt for T {
-    fn parse(&self) -> Result<Statement, Box<dyn Error>> {
-        let s = self.as_ref().as_bytes();
-
-        fn split_at(s: &[u8], c: u8) -> Result<(&[u8], &[u8]), Box<dyn Error>> {
-            let i = s
-                .iter()
-                .enumerate()
-                .filter(|(_, x)| **x == c)
-                .map(|(i, _)| i)
-                .next()
-                .map(Ok)
-                .unwrap_or_else(|| {
-                    Err(SimpleError::new(format!(
-                        "malformed rule, missing '{}'",
-                        c as char
-                    )))
-                })?;
-            let (a, b) = s.split_at(i);
-            Ok((a, &b[1..]))
+    fn parse(&self) -> Result<Statement, SimpleError> {
+        fn split_once(s: &str, c: char) -> Result<(&str, &str), SimpleError> {
+            s.split_once(c)
+                .ok_or_else(|| SimpleError::new(format!("malformed rule, missing '{}'", c)))
+        }
+
+        fn parse_position(s: &str) -> Result<u8, SimpleError> {
+            s.trim().parse().map_err(|e| {
+                SimpleError::new(format!("malformed rule position '{}': {}", s.trim(), e))
+            })
         }
 
-        let (rule, password) = split_at(s, b':')?;
-        let (range, character) = split_at(rule, b' ')?;
-        let (start, end) = split_at(range, b'-')?;
+        let (rule, password) = split_once(self.as_ref(), ':')?;
+        let (range, character) = rule.trim().split_once(' ').unwrap_or((rule.trim(), ""));
+        let (start, end) = split_once(range, '-')?;
+
+        let character = match character.trim().as_bytes() {
+            [c] => *c,
+            [] => return Err(SimpleError::new("malformed rule, missing character")),
+            _ => {
+                return Err(SimpleError::new(format!(
+                    "malformed rule, expected a single character but got '{}'",
+                    character.trim()
+                )))
+            }
+        };
 
         Ok(Statement {
             rule: Rule {
-                start: std::str::from_utf8(start)?.parse()?,
-                end: std::str::from_utf8(end)?.parse()?,
-                character: character[0],
+                start: parse_position(start)?,
+                end: parse_position(end)?,
+                character,
             },
-            password: Password(password.to_vec()),
+            password: Password(password.trim().as_bytes().to_vec()),
         })
     }
 }
 
-fn sum_by_rule_application<F>(f: F) -> Result<usize, Box<dyn Error>>
-where
-    F: Fn(Statement) -> usize,
-{
-    Ok(BufReader::new(std::fs::File::open("input.txt")?)
-        .lines()
-        .map::<Result<Statement, Box<dyn Error>>, _>(|r| Ok(r?.parse()?))
-        .map::<Result<usize, Box<dyn Error>>, _>(|r| Ok(f(r?)))
-        .fold_results(0, std::ops::Add::add)?)
+struct Verdict {
+    valid: bool,
+    reason: String,
 }
 
-fn part1() -> Result<usize, Box<dyn Error>> {
-    sum_by_rule_application(|statement| {
+trait PasswordPolicy {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError>;
+}
+
+struct SledRental;
+
+impl PasswordPolicy for SledRental {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError> {
+        let Rule {
+            start,
+            end,
+            character,
+        } = statement.rule;
         let char_count = statement
             .password
             .0
             .iter()
-            .filter(|c| **c == statement.rule.character)
+            .filter(|c| **c == character)
             .count();
-        if statement.rule.start as usize <= char_count && char_count <= statement.rule.end as usize
-        {
-            1
-        } else {
-            0
+        Ok(Verdict {
+            valid: start as usize <= char_count && char_count <= end as usize,
+            reason: format!(
+                "'{}' appears {} {}, expected {}-{}",
+                character as char,
+                char_count,
+                if char_count == 1 { "time" } else { "times" },
+                start,
+                end
+            ),
+        })
+    }
+}
+
+struct Toboggan;
+
+impl PasswordPolicy for Toboggan {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError> {
+        let Rule {
+            start,
+            end,
+            character,
+        } = statement.rule;
+        let at = |position: u8| {
+            (position as usize)
+                .checked_sub(1)
+                .and_then(|i| statement.password.0.get(i))
+                .map(|c| *c == character)
+                .ok_or_else(|| {
+                    SimpleError::new(format!(
+                        "position {} is out of range for password of length {} (positions start at 1)",
+                        position,
+                        statement.password.0.len()
+                    ))
+                })
+        };
+        let m1 = at(start)?;
+        let m2 = at(end)?;
+        Ok(Verdict {
+            valid: m1 ^ m2,
+            reason: match (m1, m2) {
+                (true, true) => format!("positions {} and {} both match", start, end),
+                (true, false) => format!("only position {} matches", start),
+                (false, true) => format!("only position {} matches", end),
+                (false, false) => format!("neither position {} nor {} matches", start, end),
+            },
+        })
+    }
+}
+
+struct MinLength(usize);
+
+impl PasswordPolicy for MinLength {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError> {
+        let len = statement.password.0.len();
+        Ok(Verdict {
+            valid: len >= self.0,
+            reason: format!("length {}, expected at least {}", len, self.0),
+        })
+    }
+}
+
+struct CharacterClasses(usize);
+
+impl PasswordPolicy for CharacterClasses {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError> {
+        let password = statement.password.0.as_slice();
+        let classes: [fn(&u8) -> bool; 4] = [
+            u8::is_ascii_lowercase,
+            u8::is_ascii_uppercase,
+            u8::is_ascii_digit,
+            u8::is_ascii_punctuation,
+        ];
+        let present = classes
+            .iter()
+            .filter(|class| password.iter().any(**class))
+            .count();
+        Ok(Verdict {
+            valid: present >= self.0,
+            reason: format!(
+                "{} character classes present, expected at least {}",
+                present, self.0
+            ),
+        })
+    }
+}
+
+struct RegexMatch(Regex);
+
+impl PasswordPolicy for RegexMatch {
+    fn evaluate(&self, statement: &Statement) -> Result<Verdict, SimpleError> {
+        let valid = self.0.is_match(statement.password.0.as_slice());
+        Ok(Verdict {
+            valid,
+            reason: format!(
+                "{} /{}/",
+                if valid { "matches" } else { "does not match" },
+                self.0
+            ),
+        })
+    }
+}
+
+struct Policies {
+    policies: HashMap<String, Box<dyn PasswordPolicy>>,
+}
+
+impl Default for Policies {
+    fn default() -> Self {
+        let mut policies = Policies {
+            policies: HashMap::new(),
+        };
+        policies.register("sled-rental", SledRental);
+        policies.register("toboggan", Toboggan);
+        policies
+    }
+}
+
+impl Policies {
+    fn register<P: PasswordPolicy + 'static>(&mut self, name: &str, policy: P) {
+        self.policies.insert(String::from(name), Box::new(policy));
+    }
+
+    fn define(&mut self, definition: &str) -> Result<(), Box<dyn Error>> {
+        let malformed = || {
+            SimpleError::new(format!(
+                "malformed policy definition {}, expected name=kind:argument",
+                definition
+            ))
+        };
+        let (name, spec) = definition.split_once('=').ok_or_else(malformed)?;
+        let (kind, argument) = spec.split_once(':').ok_or_else(malformed)?;
+        match kind {
+            "min-length" => self.register(name, MinLength(argument.parse()?)),
+            "classes" => self.register(name, CharacterClasses(argument.parse()?)),
+            "regex" => self.register(name, RegexMatch(Regex::new(argument)?)),
+            k => return Err(SimpleError::new(format!("unknown policy kind {}", k)).into()),
         }
-    })
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<&dyn PasswordPolicy, SimpleError> {
+        self.policies.get(name).map(Box::as_ref).ok_or_else(|| {
+            SimpleError::new(format!(
+                "unknown policy {}, expected one of: {}",
+                name,
+                self.policies.keys().sorted().join(", ")
+            ))
+        })
+    }
 }
 
-fn part2() -> Result<usize, Box<dyn Error>> {
-    sum_by_rule_application(|statement| {
-        let c1 = statement.password.0[statement.rule.start as usize];
-        let c2 = statement.password.0[statement.rule.end as usize];
-        if (c1 == statement.rule.character) ^ (c2 == statement.rule.character) {
-            1
-        } else {
-            0
+type Line = (String, Result<Statement, SimpleError>);
+
+fn get_lines() -> Result<Vec<Line>, Box<dyn Error>> {
+    BufReader::new(std::fs::File::open("input.txt")?)
+        .lines()
+        .map(|r| {
+            let line = r?;
+            let statement = line.parse();
+            Ok((line, statement))
+        })
+        .collect()
+}
+
+fn get_statements() -> Result<Vec<Statement>, Box<dyn Error>> {
+    get_lines()?
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, statement))| {
+            statement.map_err(|e| SimpleError::new(format!("line {}: {}", i + 1, e)).into())
+        })
+        .collect()
+}
+
+fn evaluate_all(
+    policy: &dyn PasswordPolicy,
+    statements: &[Statement],
+) -> Result<Vec<Verdict>, SimpleError> {
+    statements
+        .iter()
+        .enumerate()
+        .map(|(i, statement)| {
+            policy
+                .evaluate(statement)
+                .map_err(|e| SimpleError::new(format!("line {}: {}", i + 1, e)))
+        })
+        .collect()
+}
+
+fn count_valid(policy: &dyn PasswordPolicy) -> Result<usize, Box<dyn Error>> {
+    let statements = get_statements()?;
+    Ok(evaluate_all(policy, statements.as_slice())?
+        .iter()
+        .filter(|v| v.valid)
+        .count())
+}
+
+fn print_report(
+    name: &str,
+    policy: &dyn PasswordPolicy,
+    as_json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let lines = get_lines()?;
+    let verdicts = lines
+        .iter()
+        .map(|(_, statement)| {
+            statement
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|s| policy.evaluate(s))
+                .unwrap_or_else(|e| Verdict {
+                    valid: false,
+                    reason: e.to_string(),
+                })
+        })
+        .collect::<Vec<_>>();
+    let valid = verdicts.iter().filter(|v| v.valid).count();
+
+    if as_json {
+        let report_lines = lines
+            .iter()
+            .zip(verdicts.iter())
+            .enumerate()
+            .map(|(i, ((input, statement), verdict))| {
+                let statement = statement.as_ref().ok();
+                json!({
+                    "line": i + 1,
+                    "input": input,
+                    "rule": statement.map(|s| s.rule.to_string()),
+                    "password": statement.map(|s| s.password.to_string()),
+                    "valid": verdict.valid,
+                    "reason": verdict.reason,
+                })
+            })
+            .collect::<Vec<_>>();
+        let report = json!({
+            "policy": name,
+            "valid": valid,
+            "invalid": lines.len() - valid,
+            "lines": report_lines,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("policy {}:", name);
+    for (i, ((input, statement), verdict)) in lines.iter().zip(verdicts.iter()).enumerate() {
+        let status = if verdict.valid { "pass" } else { "fail" };
+        match statement {
+            Ok(statement) => println!(
+                "line {}: {}: {}: {} ({})",
+                i + 1,
+                statement.rule,
+                statement.password,
+                status,
+                verdict.reason
+            ),
+            Err(_) => println!("line {}: {}: {} ({})", i + 1, input, status, verdict.reason),
         }
-    })
+    }
+    println!("valid: {}, invalid: {}", valid, lines.len() - valid);
+    Ok(())
+}
+
+fn part1(policies: &Policies) -> Result<usize, Box<dyn Error>> {
+    count_valid(policies.get("sled-rental")?)
+}
+
+fn part2(policies: &Policies) -> Result<usize, Box<dyn Error>> {
+    count_valid(policies.get("toboggan")?)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!("part 1: {}", part1()?);
-    println!("part 2: {}", part2()?);
+    let mut policies = Policies::default();
+    let mut selected = Vec::new();
+    let mut report = false;
+    let mut as_json = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--report" {
+            report = true;
+        } else if arg == "--json" {
+            as_json = true;
+        } else if arg == "--define" {
+            let definition = args
+                .next()
+                .ok_or_else(|| SimpleError::new("--define requires a value"))?;
+            policies.define(definition.as_str())?;
+        } else {
+            selected.push(arg);
+        }
+    }
+
+    if report {
+        if selected.is_empty() {
+            selected = vec![String::from("sled-rental"), String::from("toboggan")];
+        }
+        for name in selected {
+            print_report(name.as_str(), policies.get(name.as_str())?, as_json)?;
+        }
+        return Ok(());
+    }
+
+    if selected.is_empty() {
+        println!("part 1: {}", part1(&policies)?);
+        println!("part 2: {}", part2(&policies)?);
+    }
+    for name in selected {
+        println!("{}: {}", name, count_valid(policies.get(name.as_str())?)?);
+    }
     Ok(())
 }
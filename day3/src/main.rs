@@ -1,9 +1,10 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use simple_error::SimpleError;
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufReader, Read};
-use std::ops::Index;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::str::FromStr;
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 enum Cell {
@@ -11,26 +12,54 @@ enum Cell {
     Tree,
 }
 
+#[derive(Eq, PartialEq, Copy, Clone)]
+enum Boundary {
+    Repeat,
+    Wall,
+    Torus,
+}
+
+impl FromStr for Boundary {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "repeat" => Ok(Boundary::Repeat),
+            "wall" => Ok(Boundary::Wall),
+            "torus" => Ok(Boundary::Torus),
+            _ => Err(SimpleError::new(format!(
+                "unknown boundary {}, expected repeat, wall or torus",
+                s
+            ))),
+        }
+    }
+}
+
 struct Region {
     data: Vec<Cell>,
     rows: usize,
+    boundary: Boundary,
 }
 
 impl Region {
     fn cols(&self) -> usize {
         self.data.len() / self.rows
     }
-}
 
-impl Index<(usize, usize)> for Region {
-    type Output = Cell;
-
-    fn index(&self, index: (usize, usize)) -> &Self::Output {
-        let r = index.0;
-        let c = index.1 % self.cols();
-        assert!(r < self.rows);
+    fn locate(&self, r: isize, c: isize) -> Option<(usize, usize)> {
+        let (rows, cols) = (self.rows as isize, self.cols() as isize);
+        let inside = |i: isize, len: isize| if (0..len).contains(&i) { Some(i) } else { None };
+        let (r, c) = match self.boundary {
+            Boundary::Repeat => (inside(r, rows)?, c.rem_euclid(cols)),
+            Boundary::Wall => (inside(r, rows)?, inside(c, cols)?),
+            Boundary::Torus => (r.rem_euclid(rows), c.rem_euclid(cols)),
+        };
+        Some((r as usize, c as usize))
+    }
 
-        &self.data[r * self.cols() + c]
+    fn get(&self, r: isize, c: isize) -> Option<Cell> {
+        self.locate(r, c)
+            .map(|(r, c)| self.data[r * self.cols() + c])
     }
 }
 
@@ -40,41 +69,167 @@ impl<T: AsRef<str>> TryFrom<StrWrapper<T>> for Region {
     type Error = SimpleError;
 
     fn try_from(value: StrWrapper<T>) -> Result<Self, Self::Error> {
-        let text = value.0.as_ref();
-        let cols: usize = text
-            .find("\n")
-            .map(Ok)
-            .unwrap_or(Err(SimpleError::new("no newline in input")))?;
-        let data = text
-            .split("\n")
-            .flat_map(str::chars)
-            .map(|c| match c {
-                '.' => Ok(Cell::Empty),
-                '#' => Ok(Cell::Tree),
-                _ => Err(SimpleError::new(format!("unknown cell type {}", c))),
-            })
-            .collect::<Result<Vec<_>, SimpleError>>()?;
+        let mut lines = value
+            .0
+            .as_ref()
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        let cols = match lines.first() {
+            Some(line) => line.chars().count(),
+            None => return Err(SimpleError::new("map is empty")),
+        };
+        if cols == 0 {
+            return Err(SimpleError::new("row 1 is empty"));
+        }
+
+        let mut data = Vec::with_capacity(lines.len() * cols);
+        for (r, line) in lines.iter().enumerate() {
+            let width = line.chars().count();
+            if width != cols {
+                return Err(SimpleError::new(format!(
+                    "row {} has width {}, expected {}",
+                    r + 1,
+                    width,
+                    cols
+                )));
+            }
+            for (c, cell) in line.chars().enumerate() {
+                data.push(match cell {
+                    '.' => Cell::Empty,
+                    '#' => Cell::Tree,
+                    _ => {
+                        return Err(SimpleError::new(format!(
+                            "unknown cell type '{}' at row {}, column {}",
+                            cell,
+                            r + 1,
+                            c + 1
+                        )))
+                    }
+                });
+            }
+        }
         Ok(Region {
-            rows: data.len() / cols,
+            rows: lines.len(),
             data,
+            boundary: Boundary::Repeat,
         })
     }
 }
 
-fn traverse_region(region: &Region, ci: usize, ri: usize) -> usize {
-    let mut r = 0;
-    let mut c = 0;
-    let mut t = 0;
-    while r < region.rows {
-        if region[(r, c)] == Cell::Tree {
-            t += 1;
+fn traverse_path(
+    region: &Region,
+    ci: isize,
+    ri: usize,
+) -> impl Iterator<Item = (isize, isize)> + '_ {
+    (0..)
+        .map(move |i| (i * ri as isize, i * ci))
+        .take_while(move |(r, c)| region.locate(*r, *c).is_some())
+        .enumerate()
+        .take_while(move |(i, (r, c))| {
+            *i == 0 || region.boundary != Boundary::Torus || region.locate(*r, *c) != Some((0, 0))
+        })
+        .map(|(_, p)| p)
+}
+
+fn traverse_region(region: &Region, ci: isize, ri: usize) -> usize {
+    traverse_path(region, ci, ri)
+        .filter(|(r, c)| region.get(*r, *c) == Some(Cell::Tree))
+        .count()
+}
+
+#[derive(Eq, PartialEq, Copy, Clone)]
+enum Mark {
+    Empty,
+    Tree,
+    Hit,
+    Clear,
+}
+
+impl Mark {
+    fn symbol(self) -> char {
+        match self {
+            Mark::Empty => '.',
+            Mark::Tree => '#',
+            Mark::Hit => 'O',
+            Mark::Clear => 'X',
         }
+    }
 
-        c += ci;
-        r += ri;
+    fn color(self) -> [u8; 3] {
+        match self {
+            Mark::Empty => [255, 255, 255],
+            Mark::Tree => [34, 139, 34],
+            Mark::Hit => [220, 20, 60],
+            Mark::Clear => [30, 144, 255],
+        }
     }
+}
+
+fn render_path(region: &Region, ci: isize, ri: usize) -> Vec<Vec<Mark>> {
+    let cols = region.cols() as isize;
+    let path = traverse_path(region, ci, ri)
+        .map(|(r, c)| match region.boundary {
+            Boundary::Repeat => (r, c),
+            _ => region
+                .locate(r, c)
+                .map(|(r, c)| (r as isize, c as isize))
+                .unwrap(),
+        })
+        .collect::<Vec<_>>();
+    let leftmost = path.iter().map(|(_, c)| *c).min().unwrap_or(0);
+    let rightmost = path.iter().map(|(_, c)| *c).max().unwrap_or(0);
+    let offset = leftmost.div_euclid(cols) * cols;
+    let width = (rightmost.div_euclid(cols) + 1) * cols - offset;
 
-    t
+    let mut marks = (0..region.rows as isize)
+        .map(|r| {
+            (offset..offset + width)
+                .map(|c| {
+                    match region.data[r as usize * cols as usize + c.rem_euclid(cols) as usize] {
+                        Cell::Empty => Mark::Empty,
+                        Cell::Tree => Mark::Tree,
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for (r, c) in path {
+        let mark = &mut marks[r as usize][(c - offset) as usize];
+        *mark = match *mark {
+            Mark::Empty | Mark::Clear => Mark::Clear,
+            Mark::Tree | Mark::Hit => Mark::Hit,
+        };
+    }
+    marks
+}
+
+fn render_text(marks: &[Vec<Mark>]) -> String {
+    marks
+        .iter()
+        .map(|row| row.iter().copied().map(Mark::symbol).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_ppm<W: Write>(marks: &[Vec<Mark>], scale: usize, mut out: W) -> std::io::Result<()> {
+    let width = marks.first().map(Vec::len).unwrap_or(0) * scale;
+    let height = marks.len() * scale;
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for row in marks {
+        let line = row
+            .iter()
+            .flat_map(|m| std::iter::repeat_n(m.color(), scale))
+            .flatten()
+            .collect::<Vec<u8>>();
+        for _ in 0..scale {
+            out.write_all(line.as_slice())?;
+        }
+    }
+    out.flush()
 }
 
 fn part1(region: &Region) -> usize {
@@ -88,11 +243,117 @@ fn part2(region: &Region) -> usize {
         .product()
 }
 
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+struct Slope {
+    right: isize,
+    down: usize,
+}
+
+struct SlopeRanking {
+    ranking: Vec<(Slope, usize)>,
+}
+
+impl SlopeRanking {
+    fn fewest_trees(&self) -> Option<(Slope, usize)> {
+        self.ranking.first().copied()
+    }
+
+    fn most_trees(&self) -> Option<(Slope, usize)> {
+        self.ranking.last().copied()
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn search_slopes(region: &Region, max_right: isize, max_down: usize) -> SlopeRanking {
+    let slopes = (-max_right.abs()..=max_right.abs())
+        .flat_map(|right| (1..=max_down).map(move |down| Slope { right, down }))
+        .filter(|slope| gcd(slope.right.unsigned_abs(), slope.down) == 1)
+        .collect::<Vec<_>>();
+    let mut ranking = slopes
+        .into_par_iter()
+        .map(|slope| (slope, traverse_region(region, slope.right, slope.down)))
+        .collect::<Vec<_>>();
+    ranking.sort_by_key(|(slope, trees)| (*trees, *slope));
+    SlopeRanking { ranking }
+}
+
+fn print_slope_search(region: &Region, max_right: isize, max_down: usize) {
+    let search = search_slopes(region, max_right, max_down);
+    for (slope, trees) in search.ranking.iter() {
+        println!(
+            "right {}, down {}: {} trees",
+            slope.right, slope.down, trees
+        );
+    }
+    if let Some((slope, trees)) = search.fewest_trees() {
+        println!(
+            "fewest trees: right {}, down {} ({} trees)",
+            slope.right, slope.down, trees
+        );
+    }
+    if let Some((slope, trees)) = search.most_trees() {
+        println!(
+            "most trees: right {}, down {} ({} trees)",
+            slope.right, slope.down, trees
+        );
+    }
+}
+
+fn arg<T: FromStr>(args: &[String], i: usize, default: T) -> Result<T, Box<dyn Error>>
+where
+    T::Err: Error + 'static,
+{
+    Ok(args
+        .get(i)
+        .map(|a| a.parse())
+        .transpose()?
+        .unwrap_or(default))
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let mut text = String::new();
     BufReader::new(File::open("input.txt")?).read_to_string(&mut text)?;
 
-    let region = StrWrapper(text).try_into()?;
+    let mut region: Region = StrWrapper(text).try_into()?;
+
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|a| a == "--boundary") {
+        region.boundary = arg(args.as_slice(), i + 1, Boundary::Repeat)?;
+        args.drain(i..(i + 2).min(args.len()));
+    }
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    match args.first().map(String::as_str) {
+        Some("search") => {
+            print_slope_search(&region, arg(&args, 1, 7)?, arg(&args, 2, 2)?);
+            return Ok(());
+        }
+        Some("render") => {
+            let marks = render_path(&region, arg(&args, 1, 3)?, arg(&args, 2, 1)?.max(1));
+            match option("--ppm") {
+                Some(path) => {
+                    let scale = option("--scale")
+                        .map(|s| s.parse())
+                        .transpose()?
+                        .unwrap_or(4);
+                    write_ppm(marks.as_slice(), scale, BufWriter::new(File::create(path)?))?;
+                }
+                None => println!("{}", render_text(marks.as_slice())),
+            }
+            return Ok(());
+        }
+        _ => (),
+    }
 
     println!("part 1: {}", part1(&region));
     println!("part 2: {}", part2(&region));
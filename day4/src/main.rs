@@ -1,117 +1,561 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
 use simple_error::SimpleError;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufReader, Read};
-
-fn parse_passports(text: &str) -> Result<Vec<HashMap<String, String>>, SimpleError> {
-    text.split("\n\n")
-        .map(|p| {
-            p.split_ascii_whitespace()
-                .map(|e| {
-                    let mut d = e.split(":");
-                    let (a, b) = match (d.next(), d.next(), d.next()) {
-                        (Some(a), Some(b), None) => (a, b),
-                        _ => return Err(SimpleError::new("unexpected number of fields")),
-                    };
-                    Ok((String::from(a), String::from(b)))
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+#[derive(Deserialize)]
+struct Bounds {
+    min: i64,
+    max: i64,
+}
+
+impl Bounds {
+    fn check(&self, value: &str) -> Result<(), String> {
+        let v = value
+            .parse::<i64>()
+            .map_err(|_| String::from("is not a number"))?;
+        if (self.min..=self.max).contains(&v) {
+            Ok(())
+        } else {
+            Err(format!("outside {}-{}", self.min, self.max))
+        }
+    }
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(pattern.as_str()).map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FieldRule {
+    Range {
+        #[serde(flatten)]
+        bounds: Bounds,
+    },
+    Units {
+        units: BTreeMap<String, Bounds>,
+    },
+    Regex {
+        #[serde(deserialize_with = "deserialize_regex")]
+        pattern: Regex,
+    },
+    OneOf {
+        values: HashSet<String>,
+    },
+}
+
+impl FieldRule {
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            FieldRule::Range { bounds } => bounds.check(value),
+            FieldRule::Units { units } => units
+                .iter()
+                .find_map(|(unit, bounds)| {
+                    value.strip_suffix(unit.as_str()).map(|v| bounds.check(v))
                 })
-                .collect::<Result<HashMap<String, String>, SimpleError>>()
-        })
-        .collect()
-}
-
-fn part1(passports: &[HashMap<String, String>]) -> usize {
-    passports
-        .iter()
-        .map(|p| {
-            [
-                p.contains_key("byr"),
-                p.contains_key("iyr"),
-                p.contains_key("eyr"),
-                p.contains_key("hgt"),
-                p.contains_key("hcl"),
-                p.contains_key("ecl"),
-                p.contains_key("pid"),
-            ]
-        })
-        .filter(|r| r.iter().all(|a| *a))
-        .count()
-}
-
-fn part2(passports: &[HashMap<String, String>]) -> usize {
-    let valid_eye_colors: HashSet<_> = vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]
-        .into_iter()
-        .collect();
-    passports
-        .iter()
-        .map(|p| {
-            [
-                p.get("byr")
-                    .map(|byr| (1920..=2002).contains(&byr.parse::<u16>().unwrap_or(0)))
-                    .unwrap_or(false),
-                p.get("iyr")
-                    .map(|iyr| (2010..=2020).contains(&iyr.parse::<u16>().unwrap_or(0)))
-                    .unwrap_or(false),
-                p.get("eyr")
-                    .map(|eyr| (2020..=2030).contains(&eyr.parse::<u16>().unwrap_or(0)))
-                    .unwrap_or(false),
-                p.get("hgt")
-                    .map(|hgt| {
-                        if hgt.contains("cm") {
-                            (150..=193).contains(
-                                &hgt.split("cm")
-                                    .next()
-                                    .unwrap_or("")
-                                    .parse::<u8>()
-                                    .unwrap_or(0),
-                            )
-                        } else if hgt.contains("in") {
-                            (59..=76).contains(
-                                &hgt.split("in")
-                                    .next()
-                                    .unwrap_or("")
-                                    .parse::<u8>()
-                                    .unwrap_or(0),
-                            )
-                        } else {
-                            false
-                        }
-                    })
-                    .unwrap_or(false),
-                p.get("hcl")
-                    .map(|hcl| {
-                        if hcl.chars().next() != Some('#') {
-                            false
-                        } else {
-                            hcl.chars().skip(1).count() == 6
-                                && hcl
-                                    .chars()
-                                    .skip(1)
-                                    .all(|c| ('0' <= c && c <= '9') || ('a' <= c && c <= 'f'))
-                        }
-                    })
-                    .unwrap_or(false),
-                p.get("ecl")
-                    .map(|ecl| valid_eye_colors.contains(ecl.as_str()))
-                    .unwrap_or(false),
-                p.get("pid")
-                    .map(|pid| pid.len() == 9 && pid.chars().all(|c| '0' <= c && c <= '9'))
-                    .unwrap_or(false),
-            ]
-        })
-        .filter(|r| r.iter().all(|a| *a))
-        .count()
+                .unwrap_or_else(|| {
+                    Err(format!(
+                        "has no {} unit",
+                        units.keys().cloned().collect::<Vec<_>>().join(" or ")
+                    ))
+                }),
+            FieldRule::Regex { pattern } if pattern.is_match(value) => Ok(()),
+            FieldRule::Regex { pattern } => Err(format!("does not match {}", pattern)),
+            FieldRule::OneOf { values } if values.contains(value) => Ok(()),
+            FieldRule::OneOf { values } => {
+                let mut values = values.iter().cloned().collect::<Vec<_>>();
+                values.sort();
+                Err(format!("is not one of {}", values.join(", ")))
+            }
+        }
+    }
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct FieldSchema {
+    name: String,
+    #[serde(default = "required_by_default")]
+    required: bool,
+    rule: Option<FieldRule>,
+}
+
+#[derive(Deserialize)]
+struct Schema {
+    fields: Vec<FieldSchema>,
+}
+
+impl Schema {
+    fn load(path: &str) -> Result<Schema, Box<dyn Error>> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    fn has_required_fields(&self, passport: &HashMap<String, String>) -> bool {
+        self.fields
+            .iter()
+            .filter(|f| f.required)
+            .all(|f| passport.contains_key(f.name.as_str()))
+    }
+
+    fn allowed_values(&self, field: &str) -> Option<&HashSet<String>> {
+        self.fields
+            .iter()
+            .find(|f| f.name == field)
+            .and_then(|f| match &f.rule {
+                Some(FieldRule::OneOf { values }) => Some(values),
+                _ => None,
+            })
+    }
+
+    fn check(&self, passport: &HashMap<String, String>) -> Vec<PassportError> {
+        self.fields
+            .iter()
+            .filter_map(|f| match (passport.get(f.name.as_str()), &f.rule) {
+                (None, _) if f.required => Some(PassportError::Missing(f.name.clone())),
+                (None, _) | (Some(_), None) => None,
+                (Some(value), Some(rule)) => {
+                    rule.check(value)
+                        .err()
+                        .map(|reason| PassportError::Invalid {
+                            field: f.name.clone(),
+                            value: value.clone(),
+                            reason,
+                        })
+                }
+            })
+            .collect()
+    }
+
+    fn validate(&self, passport: &HashMap<String, String>) -> Result<Passport, Vec<PassportError>> {
+        let mut errors = self.check(passport);
+        match Passport::parse(passport, self) {
+            Ok(passport) if errors.is_empty() => return Ok(passport),
+            Ok(_) => (),
+            Err(typed) => {
+                for error in typed {
+                    if errors.iter().all(|e| e.field() != error.field()) {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+        Err(errors)
+    }
+
+    fn is_valid(&self, passport: &HashMap<String, String>) -> bool {
+        self.validate(passport).is_ok()
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Height {
+    Cm(u8),
+    In(u8),
+}
+
+impl Display for Height {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Height::Cm(h) => write!(f, "{}cm", h),
+            Height::In(h) => write!(f, "{}in", h),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct HairColor([u8; 3]);
+
+impl Display for HairColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+struct EyeColor(String);
+
+impl Display for EyeColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+fn parse_eye_color(value: &str, known: Option<&HashSet<String>>) -> Result<EyeColor, String> {
+    match known {
+        Some(known) if !known.contains(value) => Err(String::from("is not a known eye color")),
+        _ => Ok(EyeColor(String::from(value))),
+    }
+}
+
+struct PassportId(String);
+
+struct Passport {
+    birth_year: u16,
+    issue_year: u16,
+    expiration_year: u16,
+    height: Height,
+    hair_color: HairColor,
+    eye_color: EyeColor,
+    passport_id: PassportId,
+    country_id: Option<String>,
+}
+
+impl Display for Passport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid:{} byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{}",
+            self.passport_id.0,
+            self.birth_year,
+            self.issue_year,
+            self.expiration_year,
+            self.height,
+            self.hair_color,
+            self.eye_color
+        )?;
+        if let Some(cid) = &self.country_id {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
+
+enum PassportError {
+    Missing(String),
+    Invalid {
+        field: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl PassportError {
+    fn field(&self) -> &str {
+        match self {
+            PassportError::Missing(field) | PassportError::Invalid { field, .. } => field,
+        }
+    }
+}
+
+impl Display for PassportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassportError::Missing(field) => write!(f, "missing {}", field),
+            PassportError::Invalid {
+                field,
+                value,
+                reason,
+            } => write!(f, "{}: {} {}", field, value, reason),
+        }
+    }
+}
+
+fn parse_field<T>(
+    fields: &HashMap<String, String>,
+    field: &'static str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, PassportError> {
+    let value = fields
+        .get(field)
+        .ok_or_else(|| PassportError::Missing(String::from(field)))?;
+    parse(value.as_str()).map_err(|reason| PassportError::Invalid {
+        field: String::from(field),
+        value: value.clone(),
+        reason,
+    })
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| {
+        if !value.is_empty() && value.bytes().all(|c| c.is_ascii_digit()) {
+            String::from("is too large")
+        } else {
+            String::from("is not a number")
+        }
+    })
+}
+
+fn parse_height(value: &str) -> Result<Height, String> {
+    if let Some(cm) = value.strip_suffix("cm") {
+        parse_number(cm).map(Height::Cm)
+    } else if let Some(inches) = value.strip_suffix("in") {
+        parse_number(inches).map(Height::In)
+    } else {
+        Err(String::from("has no cm or in unit"))
+    }
+}
+
+fn parse_hair_color(value: &str) -> Result<HairColor, String> {
+    let hex = value
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6 && h.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')))
+        .ok_or_else(|| String::from("is not a color of the form #rrggbb"))?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok(HairColor([channel(0), channel(2), channel(4)]))
+}
+
+fn parse_passport_id(value: &str) -> Result<PassportId, String> {
+    if value.len() == 9 && value.bytes().all(|c| c.is_ascii_digit()) {
+        Ok(PassportId(String::from(value)))
+    } else {
+        Err(String::from("is not a 9-digit number"))
+    }
+}
+
+impl Passport {
+    fn parse(
+        fields: &HashMap<String, String>,
+        schema: &Schema,
+    ) -> Result<Self, Vec<PassportError>> {
+        fn take<T>(r: Result<T, PassportError>, errors: &mut Vec<PassportError>) -> Option<T> {
+            r.map_err(|e| errors.push(e)).ok()
+        }
+
+        let mut errors = Vec::new();
+        let birth_year = take(parse_field(fields, "byr", parse_number), &mut errors);
+        let issue_year = take(parse_field(fields, "iyr", parse_number), &mut errors);
+        let expiration_year = take(parse_field(fields, "eyr", parse_number), &mut errors);
+        let height = take(parse_field(fields, "hgt", parse_height), &mut errors);
+        let hair_color = take(parse_field(fields, "hcl", parse_hair_color), &mut errors);
+        let eye_colors = schema.allowed_values("ecl");
+        let eye_color = take(
+            parse_field(fields, "ecl", |v| parse_eye_color(v, eye_colors)),
+            &mut errors,
+        );
+        let passport_id = take(parse_field(fields, "pid", parse_passport_id), &mut errors);
+        let country_id = fields.get("cid").cloned();
+
+        match (
+            birth_year,
+            issue_year,
+            expiration_year,
+            height,
+            hair_color,
+            eye_color,
+            passport_id,
+        ) {
+            (
+                Some(birth_year),
+                Some(issue_year),
+                Some(expiration_year),
+                Some(height),
+                Some(hair_color),
+                Some(eye_color),
+                Some(passport_id),
+            ) => Ok(Passport {
+                birth_year,
+                issue_year,
+                expiration_year,
+                height,
+                hair_color,
+                eye_color,
+                passport_id,
+                country_id,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+enum ParseMode<'a> {
+    Lenient,
+    Strict { known_fields: HashSet<&'a str> },
+}
+
+struct Record {
+    index: usize,
+    line: usize,
+    fields: HashMap<String, String>,
+    problems: Vec<String>,
+}
+
+struct PassportReader<'a, R: BufRead> {
+    reader: R,
+    mode: &'a ParseMode<'a>,
+    line: usize,
+    records: usize,
+    buf: String,
+}
+
+impl<'a, R: BufRead> PassportReader<'a, R> {
+    fn new(reader: R, mode: &'a ParseMode<'a>) -> Self {
+        PassportReader {
+            reader,
+            mode,
+            line: 0,
+            records: 0,
+            buf: String::new(),
+        }
+    }
+
+    fn parse_line(
+        &self,
+        record: &mut Record,
+        seen_at: &mut HashMap<String, (usize, usize)>,
+    ) -> Result<(), SimpleError> {
+        let line = self.buf.trim_end_matches(['\n', '\r']);
+        for entry in line.split_ascii_whitespace() {
+            let position = (
+                self.line,
+                entry.as_ptr() as usize - line.as_ptr() as usize + 1,
+            );
+            let mut d = entry.split(':');
+            let (key, value) = match (d.next(), d.next(), d.next()) {
+                (Some(a), Some(b), None) => (a, b),
+                _ => {
+                    return Err(SimpleError::new(format!(
+                        "line {}, column {}: expected key:value but got {}",
+                        position.0, position.1, entry
+                    )))
+                }
+            };
+
+            if let ParseMode::Strict { known_fields } = self.mode {
+                if !known_fields.contains(key) {
+                    record.problems.push(format!(
+                        "line {}, column {}: unknown field {}",
+                        position.0, position.1, key
+                    ));
+                }
+                if let Some((first_line, first_column)) = seen_at.get(key) {
+                    record.problems.push(format!(
+                        "line {}, column {}: duplicate field {}, first seen at line {}, column {}",
+                        position.0, position.1, key, first_line, first_column
+                    ));
+                    continue;
+                }
+            }
+            seen_at.insert(String::from(key), position);
+            record.fields.insert(String::from(key), String::from(value));
+        }
+        Ok(())
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, Box<dyn Error>> {
+        let mut record = Record {
+            index: self.records + 1,
+            line: 0,
+            fields: HashMap::new(),
+            problems: Vec::new(),
+        };
+        let mut seen_at = HashMap::new();
+        loop {
+            self.buf.clear();
+            if self.reader.read_line(&mut self.buf)? == 0 {
+                break;
+            }
+            self.line += 1;
+            if self.buf.trim().is_empty() {
+                if record.fields.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            if record.fields.is_empty() {
+                record.line = self.line;
+            }
+            self.parse_line(&mut record, &mut seen_at)?;
+        }
+
+        if record.fields.is_empty() {
+            return Ok(None);
+        }
+        self.records += 1;
+        Ok(Some(record))
+    }
+}
+
+impl<'a, R: BufRead> Iterator for PassportReader<'a, R> {
+    type Item = Result<Record, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn print_report(record: &Record, schema: &Schema) {
+    match schema.validate(&record.fields) {
+        Ok(passport) => println!(
+            "passport {} (line {}): valid ({})",
+            record.index, record.line, passport
+        ),
+        Err(errors) => println!(
+            "passport {} (line {}): {}",
+            record.index,
+            record.line,
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut text = String::new();
-    BufReader::new(File::open("input.txt")?).read_to_string(&mut text)?;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let option = |name: &str, default: &'static str| -> Result<String, SimpleError> {
+        match args.iter().position(|a| a == name) {
+            Some(i) => args
+                .get(i + 1)
+                .cloned()
+                .ok_or_else(|| SimpleError::new(format!("{} requires a value", name))),
+            None => Ok(String::from(default)),
+        }
+    };
+    let schema = Schema::load(option("--schema", "schema.json")?.as_str())?;
+    let reader: Box<dyn BufRead> = match option("--input", "input.txt")?.as_str() {
+        "-" => Box::new(BufReader::new(std::io::stdin())),
+        path => Box::new(BufReader::new(File::open(path)?)),
+    };
+
+    let mode = if args.iter().any(|a| a == "--strict") {
+        ParseMode::Strict {
+            known_fields: schema.fields.iter().map(|f| f.name.as_str()).collect(),
+        }
+    } else {
+        ParseMode::Lenient
+    };
+    let report = args.iter().any(|a| a == "--report");
+
+    let mut with_required_fields = 0;
+    let mut valid = 0;
+    let mut problems = 0;
+    for record in PassportReader::new(reader, &mode) {
+        let record = record?;
+        for problem in record.problems.iter() {
+            eprintln!("{}", problem);
+        }
+        problems += record.problems.len();
 
-    let passports = parse_passports(text.as_str())?;
+        if report {
+            print_report(&record, &schema);
+        }
+        if schema.has_required_fields(&record.fields) {
+            with_required_fields += 1;
+        }
+        if schema.is_valid(&record.fields) {
+            valid += 1;
+        }
+    }
+    if problems > 0 {
+        return Err(SimpleError::new(format!("found {} problems in strict mode", problems)).into());
+    }
 
-    println!("part 1: {}", part1(passports.as_slice()));
-    println!("part 2: {}", part2(passports.as_slice()));
+    if !report {
+        println!("part 1: {}", with_required_fields);
+        println!("part 2: {}", valid);
+    }
 
     Ok(())
 }
@@ -1,63 +1,301 @@
+use simple_error::SimpleError;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-const SEATS_PER_ROW: usize = 8;
-
-fn find_seat(pass: &[u8]) -> (usize, usize) {
-    let row_id = &pass[..7];
-    let mut row = 0;
-    for c in row_id.iter().copied() {
-        row = row << 1;
-        match c {
-            b'F' => (),
-            b'B' => row += 1,
-            _ => panic!("unknown row descriptor {}", c),
+#[derive(Copy, Clone)]
+struct Alphabet {
+    front: u8,
+    back: u8,
+    left: u8,
+    right: u8,
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet {
+            front: b'F',
+            back: b'B',
+            left: b'L',
+            right: b'R',
         }
     }
+}
 
-    let seat_id = &pass[7..];
-    let mut seat = 0;
-    for c in seat_id.iter().copied() {
-        seat = seat << 1;
-        match c {
-            b'L' => (),
-            b'R' => seat += 1,
-            _ => panic!("unknown seat descriptor {}", c),
+#[derive(Copy, Clone)]
+struct Cabin {
+    row_bits: usize,
+    col_bits: usize,
+    rows: usize,
+    cols: usize,
+    alphabet: Alphabet,
+}
+
+impl Default for Cabin {
+    fn default() -> Self {
+        Cabin {
+            row_bits: 7,
+            col_bits: 3,
+            rows: 128,
+            cols: 8,
+            alphabet: Alphabet::default(),
         }
     }
+}
+
+impl Cabin {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
 
-    (row, seat)
+    fn seats(&self) -> usize {
+        self.rows() * self.cols()
+    }
 }
 
-fn seat_id(row: usize, col: usize) -> usize {
-    row * SEATS_PER_ROW + col
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct BoardingPass {
+    row: usize,
+    col: usize,
 }
 
-fn get_seats() -> Result<Vec<(usize, usize)>, std::io::Error> {
-    BufReader::new(File::open("input.txt")?)
-        .lines()
-        .map(|r| Ok(find_seat(r?.as_bytes())))
-        .collect::<Result<Vec<_>, std::io::Error>>()
+fn decode_bits(code: &[u8], low: u8, high: u8) -> Result<usize, SimpleError> {
+    let mut n = 0;
+    for c in code.iter().copied() {
+        n <<= 1;
+        if c == high {
+            n += 1;
+        } else if c != low {
+            return Err(SimpleError::new(format!(
+                "unknown descriptor '{}', expected '{}' or '{}'",
+                c as char, low as char, high as char
+            )));
+        }
+    }
+    Ok(n)
+}
+
+fn encode_bits(n: usize, bits: usize, low: u8, high: u8) -> impl Iterator<Item = char> {
+    (0..bits)
+        .rev()
+        .map(move |bit| if n >> bit & 1 == 1 { high } else { low } as char)
 }
 
-fn part1(seats: &[(usize, usize)]) -> usize {
+impl BoardingPass {
+    fn decode(code: &str, cabin: &Cabin) -> Result<Self, SimpleError> {
+        let code = code.as_bytes();
+        if code.len() != cabin.row_bits + cabin.col_bits {
+            return Err(SimpleError::new(format!(
+                "pass has {} characters, expected {}",
+                code.len(),
+                cabin.row_bits + cabin.col_bits
+            )));
+        }
+        let (row, col) = code.split_at(cabin.row_bits);
+        let Alphabet {
+            front,
+            back,
+            left,
+            right,
+        } = cabin.alphabet;
+        Ok(BoardingPass {
+            row: decode_bits(row, front, back)?,
+            col: decode_bits(col, left, right)?,
+        })
+    }
+
+    fn encode(row: usize, col: usize, cabin: &Cabin) -> Result<String, SimpleError> {
+        if row >= cabin.rows() || col >= cabin.cols() {
+            return Err(SimpleError::new(format!(
+                "seat ({}, {}) does not fit a cabin of {} rows and {} columns",
+                row,
+                col,
+                cabin.rows(),
+                cabin.cols()
+            )));
+        }
+        let Alphabet {
+            front,
+            back,
+            left,
+            right,
+        } = cabin.alphabet;
+        Ok(encode_bits(row, cabin.row_bits, front, back)
+            .chain(encode_bits(col, cabin.col_bits, left, right))
+            .collect())
+    }
+
+    fn seat_id(&self, cabin: &Cabin) -> usize {
+        self.row * cabin.cols() + self.col
+    }
+}
+
+enum PassProblem {
+    Malformed {
+        line: usize,
+        pass: String,
+        reason: SimpleError,
+    },
+    OutOfRange {
+        line: usize,
+        pass: String,
+        row: usize,
+        col: usize,
+    },
+    Duplicate {
+        seat_id: usize,
+        lines: Vec<usize>,
+    },
+}
+
+impl Display for PassProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PassProblem::Malformed { line, pass, reason } => {
+                write!(f, "line {}: malformed pass '{}': {}", line, pass, reason)
+            }
+            PassProblem::OutOfRange {
+                line,
+                pass,
+                row,
+                col,
+            } => write!(
+                f,
+                "line {}: pass '{}' decodes to row {}, column {}, which is outside the cabin",
+                line, pass, row, col
+            ),
+            PassProblem::Duplicate { seat_id, lines } => write!(
+                f,
+                "seat id {} is assigned on lines {}",
+                seat_id,
+                lines
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+fn get_seats(cabin: &Cabin) -> Result<(Vec<BoardingPass>, Vec<PassProblem>), Box<dyn Error>> {
+    let mut seats = Vec::new();
+    let mut problems = Vec::new();
+    let mut lines_by_seat = BTreeMap::<usize, Vec<usize>>::new();
+
+    for (i, line) in BufReader::new(File::open("input.txt")?).lines().enumerate() {
+        let line = line?;
+        let pass = line.trim();
+        if pass.is_empty() {
+            continue;
+        }
+        match BoardingPass::decode(pass, cabin) {
+            Err(reason) => problems.push(PassProblem::Malformed {
+                line: i + 1,
+                pass: String::from(pass),
+                reason,
+            }),
+            Ok(BoardingPass { row, col }) if row >= cabin.rows() || col >= cabin.cols() => problems
+                .push(PassProblem::OutOfRange {
+                    line: i + 1,
+                    pass: String::from(pass),
+                    row,
+                    col,
+                }),
+            Ok(seat) => {
+                let lines = lines_by_seat.entry(seat.seat_id(cabin)).or_default();
+                lines.push(i + 1);
+                if lines.len() == 1 {
+                    seats.push(seat);
+                }
+            }
+        }
+    }
+
+    problems.extend(
+        lines_by_seat
+            .into_iter()
+            .filter(|(_, lines)| lines.len() > 1)
+            .map(|(seat_id, lines)| PassProblem::Duplicate { seat_id, lines }),
+    );
+    Ok((seats, problems))
+}
+
+fn part1(seats: &[BoardingPass], cabin: &Cabin) -> usize {
     seats
         .iter()
-        .copied()
-        .map(|(row, seat)| seat_id(row, seat))
+        .map(|pass| pass.seat_id(cabin))
         .max()
         .unwrap_or(0)
 }
 
-fn part2(seats: &[(usize, usize)]) -> usize {
-    let mut filled_seats = [false; 8 * 128];
-    seats
-        .iter()
-        .copied()
-        .map(|(row, seat)| seat_id(row, seat))
-        .for_each(|seat| filled_seats[seat] = true);
-    filled_seats
+struct SeatMap {
+    cabin: Cabin,
+    occupied: Vec<bool>,
+}
+
+impl SeatMap {
+    fn new(seats: &[BoardingPass], cabin: &Cabin) -> Self {
+        let mut occupied = vec![false; cabin.seats()];
+        seats
+            .iter()
+            .map(|pass| pass.seat_id(cabin))
+            .for_each(|seat| occupied[seat] = true);
+        SeatMap {
+            cabin: *cabin,
+            occupied,
+        }
+    }
+
+    fn row(&self, row: usize) -> &[bool] {
+        let cols = self.cabin.cols();
+        &self.occupied[row * cols..(row + 1) * cols]
+    }
+
+    fn free_seats(&self) -> impl Iterator<Item = usize> + '_ {
+        self.occupied
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| !**o)
+            .map(|(i, _)| i)
+    }
+}
+
+enum FreeSeat {
+    NotFound,
+    Exactly(usize),
+    Ambiguous(Vec<usize>),
+}
+
+impl FreeSeat {
+    fn candidates(&self) -> &[usize] {
+        match self {
+            FreeSeat::NotFound => &[],
+            FreeSeat::Exactly(seat) => std::slice::from_ref(seat),
+            FreeSeat::Ambiguous(seats) => seats.as_slice(),
+        }
+    }
+}
+
+impl Display for FreeSeat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FreeSeat::NotFound => write!(f, "no free seat"),
+            FreeSeat::Exactly(seat) => write!(f, "{}", seat),
+            FreeSeat::Ambiguous(seats) => write!(f, "ambiguous: {:?}", seats),
+        }
+    }
+}
+
+fn part2(map: &SeatMap) -> FreeSeat {
+    let filled_seats = map.occupied.as_slice();
+    let mut candidates = filled_seats
         .iter()
         .cloned()
         .zip(filled_seats.iter().cloned().enumerate().skip(1))
@@ -65,14 +303,188 @@ fn part2(seats: &[(usize, usize)]) -> usize {
         .map(|((a, (i, b)), c)| ((a, b, c), i))
         .filter(|((a, b, c), _)| *a && !*b && *c)
         .map(|(_, i)| i)
-        .next()
-        .unwrap_or(0)
+        .collect::<Vec<_>>();
+    match candidates.len() {
+        0 => FreeSeat::NotFound,
+        1 => FreeSeat::Exactly(candidates.remove(0)),
+        _ => FreeSeat::Ambiguous(candidates),
+    }
+}
+
+fn print_seat_map(map: &SeatMap) {
+    let cabin = &map.cabin;
+    let free_seat = part2(map);
+    let my_seats = free_seat.candidates();
+    let row_width = (cabin.rows() - 1).to_string().len();
+
+    for row in 0..cabin.rows() {
+        let seats = map.row(row);
+        let taken = seats.iter().filter(|o| **o).count();
+        let layout = seats
+            .iter()
+            .enumerate()
+            .map(|(col, occupied)| {
+                match (occupied, my_seats.contains(&(row * cabin.cols() + col))) {
+                    (true, _) => '#',
+                    (false, true) => 'X',
+                    (false, false) => '.',
+                }
+            })
+            .collect::<String>();
+        println!(
+            "row {:>width$} |{}| {:>3}/{} ({:.0}%)",
+            row,
+            layout,
+            taken,
+            cabin.cols(),
+            100.0 * taken as f64 / cabin.cols() as f64,
+            width = row_width
+        );
+    }
+
+    let row_counts = (0..cabin.rows())
+        .map(|row| map.row(row).iter().filter(|o| **o).count())
+        .collect::<Vec<_>>();
+    println!(
+        "full rows: {}, partially filled rows: {}, empty rows: {}",
+        row_counts.iter().filter(|c| **c == cabin.cols()).count(),
+        row_counts
+            .iter()
+            .filter(|c| **c > 0 && **c < cabin.cols())
+            .count(),
+        row_counts.iter().filter(|c| **c == 0).count()
+    );
+
+    let free = map.free_seats().collect::<Vec<_>>();
+    println!("free seats: {}", free.len());
+    for seat in free {
+        println!(
+            "  row {}, column {}, seat id {}{}",
+            seat / cabin.cols(),
+            seat % cabin.cols(),
+            seat,
+            if my_seats.contains(&seat) {
+                " (your seat)"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+fn parse_cabin(args: &[String]) -> Result<Cabin, Box<dyn Error>> {
+    let option = |name: &str| -> Result<Option<&String>, SimpleError> {
+        match args.iter().position(|a| a == name) {
+            Some(i) => args
+                .get(i + 1)
+                .map(Some)
+                .ok_or_else(|| SimpleError::new(format!("{} requires a value", name))),
+            None => Ok(None),
+        }
+    };
+
+    let mut cabin = Cabin::default();
+    if let Some(bits) = option("--row-bits")? {
+        cabin.row_bits = bits.parse()?;
+    }
+    if let Some(bits) = option("--col-bits")? {
+        cabin.col_bits = bits.parse()?;
+    }
+    if let Some(alphabet) = option("--alphabet")? {
+        cabin.alphabet = match alphabet.as_bytes() {
+            [front, back, left, right] if front == back || left == right => {
+                return Err(SimpleError::new(
+                    "--alphabet needs distinct front/back and left/right characters",
+                )
+                .into())
+            }
+            [front, back, left, right] => Alphabet {
+                front: *front,
+                back: *back,
+                left: *left,
+                right: *right,
+            },
+            _ => {
+                return Err(SimpleError::new(
+                    "--alphabet expects four characters: front, back, left, right",
+                )
+                .into())
+            }
+        };
+    }
+    if cabin.row_bits + cabin.col_bits >= usize::BITS as usize {
+        return Err(SimpleError::new("cabin has too many seats").into());
+    }
+    cabin.rows = option("--rows")?
+        .map(|r| r.parse())
+        .transpose()?
+        .unwrap_or(1 << cabin.row_bits);
+    cabin.cols = option("--cols")?
+        .map(|c| c.parse())
+        .transpose()?
+        .unwrap_or(1 << cabin.col_bits);
+    if cabin.rows == 0 || cabin.cols == 0 {
+        return Err(SimpleError::new("cabin needs at least one row and one column").into());
+    }
+    if cabin.rows > 1 << cabin.row_bits || cabin.cols > 1 << cabin.col_bits {
+        return Err(SimpleError::new(format!(
+            "{} rows and {} columns cannot be encoded in {} row bits and {} column bits",
+            cabin.rows, cabin.cols, cabin.row_bits, cabin.col_bits
+        ))
+        .into());
+    }
+    Ok(cabin)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let seats = get_seats()?;
-    println!("part 1: {}", part1(seats.as_ref()));
-    println!("part 2: {}", part2(seats.as_ref()));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let cabin = parse_cabin(args.as_slice())?;
+
+    match args.first().map(String::as_str) {
+        Some("encode") => {
+            let (row, col) = match (args.get(1), args.get(2)) {
+                (Some(row), Some(col)) => (row.parse()?, col.parse()?),
+                _ => return Err(SimpleError::new("usage: encode <row> <col>").into()),
+            };
+            println!("{}", BoardingPass::encode(row, col, &cabin)?);
+            return Ok(());
+        }
+        Some("decode") => {
+            let code = args
+                .get(1)
+                .ok_or_else(|| SimpleError::new("usage: decode <pass>"))?;
+            let pass = BoardingPass::decode(code.as_str(), &cabin)?;
+            println!(
+                "row {}, column {}, seat id {}",
+                pass.row,
+                pass.col,
+                pass.seat_id(&cabin)
+            );
+            return Ok(());
+        }
+        _ => (),
+    }
+
+    let (seats, problems) = get_seats(&cabin)?;
+    if let Some("check") = args.first().map(String::as_str) {
+        for problem in problems.iter() {
+            println!("{}", problem);
+        }
+        println!("{} valid passes, {} problems", seats.len(), problems.len());
+        return Ok(());
+    }
+    for problem in problems.iter() {
+        eprintln!("{}", problem);
+    }
+
+    let map = SeatMap::new(seats.as_slice(), &cabin);
+    if let Some("map") = args.first().map(String::as_str) {
+        print_seat_map(&map);
+        return Ok(());
+    }
+
+    println!("part 1: {}", part1(seats.as_ref(), &cabin));
+    println!("part 2: {}", part2(&map));
 
     Ok(())
 }
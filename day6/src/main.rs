@@ -1,53 +1,240 @@
-use itertools::Itertools;
-use std::collections::HashSet;
+use serde_json::json;
+use simple_error::SimpleError;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufReader, Read};
 
+const QUESTIONS: u32 = 26;
+
 fn get_input() -> Result<String, std::io::Error> {
     let mut text = String::new();
     BufReader::new(File::open("input.txt")?).read_to_string(&mut text)?;
     Ok(text)
 }
 
-fn get_forms(text: &str) -> Vec<HashSet<u8>> {
-    text.split("\n\n")
-        .map(|p| {
-            p.split("\n")
-                .flat_map(|s| s.as_bytes().iter())
-                .cloned()
-                .collect()
-        })
-        .collect()
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+struct Answers(u32);
+
+impl Answers {
+    fn all() -> Self {
+        Answers((1 << QUESTIONS) - 1)
+    }
+
+    fn parse(line: &str, line_number: usize) -> Result<Self, SimpleError> {
+        line.chars()
+            .enumerate()
+            .try_fold(Answers(0), |acc, (i, c)| {
+                if c.is_ascii_lowercase() {
+                    Ok(Answers(acc.0 | 1 << (c as u8 - b'a')))
+                } else {
+                    Err(SimpleError::new(format!(
+                        "line {}, column {}: unexpected answer {:?}, expected a-z",
+                        line_number,
+                        i + 1,
+                        c
+                    )))
+                }
+            })
+    }
+
+    fn contains(self, question: u32) -> bool {
+        self.0 >> question & 1 == 1
+    }
+
+    fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
 }
 
-fn get_intersecting_forms(text: &str) -> Vec<HashSet<u8>> {
-    text.split("\n\n")
-        .map(|p| {
-            p.split("\n")
-                .filter(|l| !l.is_empty())
-                .map(|s| s.as_bytes().iter().cloned().collect::<HashSet<u8>>())
-                .fold1(|h1, h2| h1.intersection(&h2).cloned().collect())
-                .unwrap_or_else(HashSet::new)
+struct Group {
+    people: Vec<Answers>,
+}
+
+impl Group {
+    fn anyone(&self) -> Answers {
+        Answers(self.people.iter().fold(0, |acc, a| acc | a.0))
+    }
+
+    fn everyone(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::default();
+        }
+        Answers(
+            self.people
+                .iter()
+                .fold(Answers::all().0, |acc, a| acc & a.0),
+        )
+    }
+
+    fn yes_count(&self, question: u32) -> usize {
+        self.people.iter().filter(|a| a.contains(question)).count()
+    }
+
+    fn matching(&self, f: impl Fn(usize) -> bool) -> Answers {
+        Answers(
+            (0..QUESTIONS)
+                .filter(|q| f(self.yes_count(*q)))
+                .fold(0, |acc, q| acc | 1 << q),
+        )
+    }
+
+    fn exactly(&self, k: usize) -> Answers {
+        self.matching(|yes| yes == k)
+    }
+
+    fn at_least_percent(&self, p: f64) -> Answers {
+        let size = self.people.len() as f64;
+        self.matching(|yes| yes > 0 && yes as f64 * 100.0 >= p * size)
+    }
+}
+
+fn get_groups(text: &str) -> Result<Vec<Group>, SimpleError> {
+    let mut groups = Vec::new();
+    let mut people = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !people.is_empty() {
+                groups.push(Group {
+                    people: std::mem::take(&mut people),
+                });
+            }
+            continue;
+        }
+        people.push(Answers::parse(line, i + 1)?);
+    }
+    if !people.is_empty() {
+        groups.push(Group { people });
+    }
+    Ok(groups)
+}
+
+fn sum_by_query(groups: &[Group], query: impl Fn(&Group) -> Answers) -> usize {
+    groups.iter().map(|g| query(g).count()).sum()
+}
+
+fn part1(groups: &[Group]) -> usize {
+    sum_by_query(groups, Group::anyone)
+}
+
+fn part2(groups: &[Group]) -> usize {
+    sum_by_query(groups, Group::everyone)
+}
+
+struct QuestionStats {
+    question: char,
+    anyone: usize,
+    everyone: usize,
+    people: usize,
+}
+
+fn question_stats(groups: &[Group]) -> Vec<QuestionStats> {
+    (0..QUESTIONS)
+        .map(|q| QuestionStats {
+            question: (b'a' + q as u8) as char,
+            anyone: groups.iter().filter(|g| g.anyone().contains(q)).count(),
+            everyone: groups.iter().filter(|g| g.everyone().contains(q)).count(),
+            people: groups.iter().map(|g| g.yes_count(q)).sum(),
         })
         .collect()
 }
 
-fn part1(forms: &[HashSet<u8>]) -> usize {
-    forms.iter().map(HashSet::len).sum()
-}
+fn print_report(groups: &[Group], as_json: bool) -> Result<(), Box<dyn Error>> {
+    let stats = question_stats(groups);
+    let sizes = groups.iter().fold(BTreeMap::new(), |mut acc, g| {
+        *acc.entry(g.people.len()).or_insert(0) += 1;
+        acc
+    });
+    let most_common = stats.iter().max_by_key(|s| (s.people, Reverse(s.question)));
+    let least_common = stats.iter().min_by_key(|s| (s.people, s.question));
+
+    if as_json {
+        let report = json!({
+            "groups": groups.len(),
+            "questions": stats
+                .iter()
+                .map(|s| json!({
+                    "question": s.question.to_string(),
+                    "anyone": s.anyone,
+                    "everyone": s.everyone,
+                    "people": s.people,
+                }))
+                .collect::<Vec<_>>(),
+            "group_sizes": sizes
+                .iter()
+                .map(|(size, count)| json!({ "size": size, "groups": count }))
+                .collect::<Vec<_>>(),
+            "most_common": most_common.map(|s| s.question.to_string()),
+            "least_common": least_common.map(|s| s.question.to_string()),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
 
-fn part2(forms: &[HashSet<u8>]) -> usize {
-    forms.iter().map(HashSet::len).sum()
+    println!("question  anyone  everyone  people");
+    for s in stats.iter() {
+        println!(
+            "{:>8}  {:>6}  {:>8}  {:>6}",
+            s.question, s.anyone, s.everyone, s.people
+        );
+    }
+    println!();
+    println!("group size  groups");
+    for (size, count) in sizes.iter() {
+        println!("{:>10}  {:>6}", size, count);
+    }
+    println!();
+    if let (Some(most), Some(least)) = (most_common, least_common) {
+        println!(
+            "most common question: {} ({} people)",
+            most.question, most.people
+        );
+        println!(
+            "least common question: {} ({} people)",
+            least.question, least.people
+        );
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let text = get_input()?;
-    let forms = get_forms(text.as_str());
-    println!("part 1: {}", part1(forms.as_slice()));
+    let groups = get_groups(text.as_str())?;
 
-    let intersecting_forms = get_intersecting_forms(text.as_str());
-    println!("part 2: {}", part2(intersecting_forms.as_slice()));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let argument = || {
+        args.get(1)
+            .ok_or_else(|| SimpleError::new(format!("{} requires an argument", args[0])))
+    };
+    match args.first().map(String::as_str) {
+        Some("report") => print_report(groups.as_slice(), args.iter().any(|a| a == "--json"))?,
+        Some("anyone") => println!("{}", sum_by_query(groups.as_slice(), Group::anyone)),
+        Some("everyone") => println!("{}", sum_by_query(groups.as_slice(), Group::everyone)),
+        Some("exactly") => {
+            let k = argument()?.parse()?;
+            println!("{}", sum_by_query(groups.as_slice(), |g| g.exactly(k)));
+        }
+        Some("at-least-percent") => {
+            let p = argument()?.parse()?;
+            println!(
+                "{}",
+                sum_by_query(groups.as_slice(), |g| g.at_least_percent(p))
+            );
+        }
+        Some(q) => {
+            return Err(SimpleError::new(format!(
+                "unknown command {}, expected report, anyone, everyone, exactly <k> or at-least-percent <p>",
+                q
+            ))
+            .into())
+        }
+        None => {
+            println!("part 1: {}", part1(groups.as_slice()));
+            println!("part 2: {}", part2(groups.as_slice()));
+        }
+    }
 
     Ok(())
 }
@@ -1,142 +1,526 @@
-use once_cell::sync::Lazy;
-use regex::Regex;
+use num_bigint::BigUint;
+use serde_json::json;
 use simple_error::SimpleError;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-static RULE_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(no|\d+) (other|[a-z ]+) bags?,?").unwrap());
-
-static RULES_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^([a-z ]+) bags contain ([a-z, \d]+).$").unwrap());
-
 static SHINY_GOLD: &str = "shiny gold";
 
 struct Rule {
     descr: String,
-    count: u8,
-}
-
-fn parse_rules() -> Result<HashMap<String, Vec<Rule>>, Box<dyn Error>> {
-    BufReader::new(File::open("input.txt")?)
-        .lines()
-        .map::<Result<(String, Vec<Rule>), Box<dyn Error>>, _>(|l| {
-            let l = l?;
-            let captures = match RULES_REGEX.captures(l.as_str()) {
-                Some(c) => c,
-                None => Err(SimpleError::new(format!("rule did not match: {}", l)))?,
-            };
-
-            let target = match captures.get(1).unwrap().as_str() {
-                s => String::from(s),
-            };
-
-            let matches = captures.get(2).unwrap().as_str();
-            let captures: Vec<_> = RULE_REGEX
-                .captures_iter(matches)
-                .map::<Result<Rule, Box<dyn Error>>, _>(|capture| {
-                    let count = match capture.get(1).unwrap().as_str() {
-                        "no" => {
-                            return Ok(Rule {
-                                count: 0,
-                                descr: String::new(),
-                            })
-                        }
-                        e => e
-                            .trim()
-                            .parse::<u8>()
-                            .map_err(|e| SimpleError::new(format!("number not match: {}", e)))?,
-                    };
-                    let descr = capture.get(2).unwrap().as_str().to_string();
-
-                    Ok(Rule { descr, count })
-                })
-                .filter(|r| match r {
-                    Ok(r) => r.count != 0,
-                    Err(_) => true,
-                })
-                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
-
-            Ok((target, captures))
-        })
-        .collect()
+    count: u64,
 }
 
-fn part1(rules: &HashMap<String, Vec<Rule>>) -> usize {
-    let references = rules
-        .iter()
-        .flat_map(|(src, targets)| {
-            targets
-                .iter()
-                .map(move |target| (target.descr.clone(), src.clone()))
-        })
-        .fold::<HashMap<String, HashSet<String>>, _>(HashMap::new(), |mut acc, (target, dest)| {
-            (*acc.entry(target).or_default()).insert(dest);
-            acc
-        });
-    let mut can_contain_gold_bag = HashSet::new();
-    let mut left_to_check = references
-        .get(SHINY_GOLD)
-        .map(|s| s.clone())
-        .unwrap_or_else(HashSet::new);
-
-    while !left_to_check.is_empty() {
-        let mut new_left_to_check = HashSet::new();
-        for to_check in left_to_check {
-            if can_contain_gold_bag.contains(&to_check) {
-                continue;
-            }
-            if let Some(refs) = references.get(&to_check) {
-                for r in refs {
-                    new_left_to_check.insert(r.clone());
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum BagCount {
+    Small(u64),
+    Big(BigUint),
+}
+
+impl BagCount {
+    fn big(&self) -> BigUint {
+        match self {
+            BagCount::Small(n) => BigUint::from(*n),
+            BagCount::Big(n) => n.clone(),
+        }
+    }
+
+    fn add(&self, other: &BagCount) -> BagCount {
+        match (self, other) {
+            (BagCount::Small(a), BagCount::Small(b)) => match a.checked_add(*b) {
+                Some(sum) => BagCount::Small(sum),
+                None => BagCount::Big(self.big() + other.big()),
+            },
+            _ => BagCount::Big(self.big() + other.big()),
+        }
+    }
+
+    fn mul(&self, factor: u64) -> BagCount {
+        match self {
+            BagCount::Small(a) => match a.checked_mul(factor) {
+                Some(product) => BagCount::Small(product),
+                None => BagCount::Big(self.big() * factor),
+            },
+            BagCount::Big(a) => BagCount::Big(a * factor),
+        }
+    }
+}
+
+impl fmt::Display for BagCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BagCount::Small(n) => write!(f, "{}", n),
+            BagCount::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Token<'a> {
+    Word(&'a str),
+    Comma,
+    Period,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "'{}'", w),
+            Token::Comma => write!(f, "','"),
+            Token::Period => write!(f, "'.'"),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'
+}
+
+fn tokenize(line: &str) -> Result<Vec<(usize, Token<'_>)>, SimpleError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | '\t' => continue,
+            ',' => tokens.push((i, Token::Comma)),
+            '.' => tokens.push((i, Token::Period)),
+            c if is_word_char(c) => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    end = j + c.len_utf8();
                 }
+                tokens.push((i, Token::Word(&line[i..end])));
+            }
+            c => {
+                return Err(SimpleError::new(format!(
+                    "column {}: unexpected character {:?}",
+                    i + 1,
+                    c
+                )))
             }
-            can_contain_gold_bag.insert(to_check);
         }
-        left_to_check = new_left_to_check;
     }
+    Ok(tokens)
+}
 
-    can_contain_gold_bag.len()
+struct RuleParser<'a> {
+    tokens: Vec<(usize, Token<'a>)>,
+    pos: usize,
+    end: usize,
 }
 
-fn part2(rules: &HashMap<String, Vec<Rule>>) -> usize {
-    let mut bag_contains = HashMap::<String, usize>::new();
-    for (rule, _) in rules.iter().filter(|(_, l)| l.is_empty()) {
-        bag_contains.insert(rule.clone(), 0);
+impl<'a> RuleParser<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).map(|(_, t)| *t)
+    }
+
+    fn error(&self, expected: &str) -> SimpleError {
+        SimpleError::new(match self.tokens.get(self.pos) {
+            Some((i, t)) => format!("column {}: expected {}, found {}", i + 1, expected, t),
+            None => format!(
+                "column {}: expected {}, found end of line",
+                self.end + 1,
+                expected
+            ),
+        })
+    }
+
+    fn expect(&mut self, token: Token<'a>) -> Result<(), SimpleError> {
+        if self.peek() != Some(token) {
+            return Err(self.error(&token.to_string()));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn is_bag(token: Option<Token<'a>>) -> bool {
+        matches!(token, Some(Token::Word("bag")) | Some(Token::Word("bags")))
     }
 
-    while !bag_contains.contains_key(SHINY_GOLD) {
-        for (rule, contains) in rules {
-            if bag_contains.contains_key(rule) {
-                continue;
+    fn color(&mut self) -> Result<String, SimpleError> {
+        let mut words = Vec::new();
+        while let Some(Token::Word(w)) = self.peek() {
+            if Self::is_bag(Some(Token::Word(w))) {
+                break;
             }
-            let maybe_bag_count = contains
-                .iter()
-                .map(|r| {
-                    bag_contains
-                        .get(&r.descr)
-                        .map(|containing| (*containing + 1) * r.count as usize)
-                })
-                .fold(Some(0), |acc, v| match (acc, v) {
-                    (Some(a), Some(b)) => Some(a + b),
-                    _ => None,
+            words.push(w);
+            self.pos += 1;
+        }
+        if words.is_empty() {
+            return Err(self.error("a bag color"));
+        }
+        if !Self::is_bag(self.peek()) {
+            return Err(self.error("'bag' or 'bags'"));
+        }
+        self.pos += 1;
+        Ok(words.join(" "))
+    }
+
+    fn quantity(&mut self, expected: &str) -> Result<u64, SimpleError> {
+        match self.tokens.get(self.pos) {
+            Some((i, Token::Word(w))) if w.bytes().all(|b| b.is_ascii_digit()) => {
+                let count = w.parse().map_err(|_| {
+                    SimpleError::new(format!("column {}: quantity {} is too large", i + 1, w))
+                })?;
+                self.pos += 1;
+                Ok(count)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn contents(&mut self) -> Result<Vec<Rule>, SimpleError> {
+        if self.peek() == Some(Token::Word("no")) {
+            self.pos += 1;
+            self.expect(Token::Word("other"))?;
+            if !Self::is_bag(self.peek()) {
+                return Err(self.error("'bag' or 'bags'"));
+            }
+            self.pos += 1;
+            return Ok(Vec::new());
+        }
+        let mut contents = Vec::new();
+        let mut expected = "a quantity or 'no other bags'";
+        loop {
+            let count = self.quantity(expected)?;
+            let descr = self.color()?;
+            contents.push(Rule { descr, count });
+            if self.peek() != Some(Token::Comma) {
+                return Ok(contents);
+            }
+            self.pos += 1;
+            expected = "a quantity";
+        }
+    }
+
+    fn parse(line: &'a str) -> Result<(String, Vec<Rule>), SimpleError> {
+        let mut parser = RuleParser {
+            tokens: tokenize(line)?,
+            pos: 0,
+            end: line.len(),
+        };
+        let color = parser.color()?;
+        parser.expect(Token::Word("contain"))?;
+        let contents = parser.contents()?;
+        parser.expect(Token::Period)?;
+        if parser.peek().is_some() {
+            return Err(parser.error("end of line"));
+        }
+        Ok((color, contents))
+    }
+}
+
+fn parse_rules() -> Result<Rules, Box<dyn Error>> {
+    let mut rules = HashMap::new();
+    for (i, line) in BufReader::new(File::open("input.txt")?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let (color, contents) = RuleParser::parse(line)
+            .map_err(|e| SimpleError::new(format!("line {}, {}", i + 1, e)))?;
+        rules.insert(color, contents);
+    }
+    Ok(rules)
+}
+
+type Rules = HashMap<String, Vec<Rule>>;
+
+fn known_colors(rules: &Rules) -> HashSet<&str> {
+    rules
+        .iter()
+        .flat_map(|(color, contents)| {
+            std::iter::once(color.as_str()).chain(contents.iter().map(|r| r.descr.as_str()))
+        })
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn check_color(rules: &Rules, color: &str) -> Result<(), SimpleError> {
+    let known = known_colors(rules);
+    if known.contains(color) {
+        return Ok(());
+    }
+    let suggestion = known
+        .iter()
+        .map(|k| (edit_distance(color, k), *k))
+        .filter(|(distance, _)| *distance <= color.len() / 3 + 1)
+        .min();
+    Err(SimpleError::new(match suggestion {
+        Some((_, k)) => format!("unknown bag color '{}', did you mean '{}'?", color, k),
+        None => format!("unknown bag color '{}'", color),
+    }))
+}
+
+fn containers_of(rules: &Rules, color: &str, depth: Option<usize>) -> HashSet<String> {
+    let mut references = HashMap::<&str, Vec<&str>>::new();
+    for (src, targets) in rules {
+        for target in targets {
+            references
+                .entry(target.descr.as_str())
+                .or_default()
+                .push(src.as_str());
+        }
+    }
+
+    let mut containers = HashSet::new();
+    let mut left_to_check = vec![color];
+    let mut level = 0;
+    while !left_to_check.is_empty() && depth.is_none_or(|d| level < d) {
+        left_to_check = left_to_check
+            .into_iter()
+            .flat_map(|c| references.get(c).into_iter().flatten().copied())
+            .filter(|c| containers.insert(c.to_string()))
+            .collect();
+        level += 1;
+    }
+    containers
+}
+
+struct Frame<'a> {
+    color: &'a str,
+    depth: Option<usize>,
+    next: usize,
+    count: BagCount,
+}
+
+fn contents_of<'a>(
+    rules: &'a Rules,
+    color: &'a str,
+    depth: Option<usize>,
+) -> Result<BagCount, SimpleError> {
+    let mut memo = HashMap::<(&str, Option<usize>), BagCount>::new();
+    let mut on_path = HashSet::new();
+    let mut stack = Vec::<Frame>::new();
+    let mut visit = Some((color, depth));
+    loop {
+        let mut finished = None;
+        if let Some((color, depth)) = visit.take() {
+            if depth == Some(0) {
+                finished = Some(BagCount::Small(0));
+            } else if let Some(count) = memo.get(&(color, depth)) {
+                finished = Some(count.clone());
+            } else if on_path.contains(color) {
+                let cycle = stack
+                    .iter()
+                    .map(|f| f.color)
+                    .skip_while(|c| *c != color)
+                    .collect::<Vec<_>>();
+                return Err(SimpleError::new(format!(
+                    "bag rules contain a cycle: {} -> {}",
+                    cycle.join(" -> "),
+                    color
+                )));
+            } else if !rules.contains_key(color) {
+                return Err(SimpleError::new(match stack.last() {
+                    Some(parent) => format!(
+                        "no rule for bag color '{}' (contained in '{}')",
+                        color, parent.color
+                    ),
+                    None => format!("no rule for bag color '{}'", color),
+                }));
+            } else {
+                on_path.insert(color);
+                stack.push(Frame {
+                    color,
+                    depth,
+                    next: 0,
+                    count: BagCount::Small(0),
                 });
-            if let Some(count) = maybe_bag_count {
-                bag_contains.insert(rule.clone(), count);
             }
+        } else if let Some(frame) = stack.pop() {
+            on_path.remove(frame.color);
+            memo.insert((frame.color, frame.depth), frame.count.clone());
+            finished = Some(frame.count);
+        }
+
+        if let Some(inner) = finished {
+            match stack.last_mut() {
+                Some(parent) => {
+                    let r = &rules[parent.color][parent.next];
+                    parent.count = parent
+                        .count
+                        .add(&inner.add(&BagCount::Small(1)).mul(r.count));
+                    parent.next += 1;
+                }
+                None => return Ok(inner),
+            }
+        }
+        if let Some(frame) = stack.last() {
+            visit = rules[frame.color]
+                .get(frame.next)
+                .map(|r| (r.descr.as_str(), frame.depth.map(|d| d - 1)));
         }
     }
+}
+
+fn reachable_from(rules: &Rules, color: &str) -> HashSet<String> {
+    let mut reachable = HashSet::new();
+    let mut left_to_check = vec![color];
+    while let Some(c) = left_to_check.pop() {
+        if reachable.insert(c.to_string()) {
+            left_to_check.extend(rules.get(c).into_iter().flatten().map(|r| r.descr.as_str()));
+        }
+    }
+    reachable
+}
+
+fn subgraph<'a>(
+    rules: &'a Rules,
+    from: Option<&str>,
+    into: Option<&str>,
+) -> BTreeMap<&'a str, Vec<&'a Rule>> {
+    let from = from.map(|c| reachable_from(rules, c));
+    let into = into.map(|c| {
+        let mut containers = containers_of(rules, c, None);
+        containers.insert(c.to_string());
+        containers
+    });
+    let keep = |c: &str| {
+        from.as_ref().is_none_or(|s| s.contains(c)) && into.as_ref().is_none_or(|s| s.contains(c))
+    };
+    known_colors(rules)
+        .into_iter()
+        .filter(|c| keep(c))
+        .map(|c| {
+            let contents = rules
+                .get(c)
+                .into_iter()
+                .flatten()
+                .filter(|r| keep(&r.descr))
+                .collect();
+            (c, contents)
+        })
+        .collect()
+}
+
+fn render_dot(graph: &BTreeMap<&str, Vec<&Rule>>) -> String {
+    let mut dot = String::from("digraph bags {\n");
+    for (color, contents) in graph {
+        dot += &format!("    \"{}\";\n", color);
+        for r in contents {
+            dot += &format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                color, r.descr, r.count
+            );
+        }
+    }
+    dot += "}";
+    dot
+}
 
-    *bag_contains.get(SHINY_GOLD).unwrap()
+fn render_json(graph: &BTreeMap<&str, Vec<&Rule>>) -> serde_json::Result<String> {
+    let adjacency = graph
+        .iter()
+        .map(|(color, contents)| {
+            let contents = contents
+                .iter()
+                .map(|r| json!({ "color": r.descr, "count": r.count }))
+                .collect::<Vec<_>>();
+            (color.to_string(), json!(contents))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::to_string_pretty(&adjacency)
+}
+
+fn part1(rules: &Rules) -> usize {
+    containers_of(rules, SHINY_GOLD, None).len()
+}
+
+fn part2(rules: &Rules) -> Result<BagCount, SimpleError> {
+    contents_of(rules, SHINY_GOLD, None)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let rules = parse_rules()?;
 
-    println!("part 1: {}", part1(&rules));
-    println!("part 2: {}", part2(&rules));
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut option = |name: &str, multi_word: bool| -> Result<Option<String>, SimpleError> {
+        let i = match args.iter().position(|a| a == name) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let end = if multi_word {
+            args[i + 1..]
+                .iter()
+                .position(|a| a.starts_with("--"))
+                .map_or(args.len(), |j| i + 1 + j)
+        } else {
+            (i + 2).min(args.len())
+        };
+        if end == i + 1 {
+            return Err(SimpleError::new(format!("{} requires an argument", name)));
+        }
+        Ok(Some(
+            args.drain(i..end).skip(1).collect::<Vec<_>>().join(" "),
+        ))
+    };
+    let depth = option("--depth", false)?
+        .map(|d| d.parse::<usize>())
+        .transpose()?;
+    let from = option("--from", true)?;
+    let into = option("--into", true)?;
+    for color in from.iter().chain(into.iter()) {
+        check_color(&rules, color)?;
+    }
+    let color = || -> Result<String, SimpleError> {
+        let color = args[1..].join(" ");
+        if color.is_empty() {
+            return Err(SimpleError::new(format!("{} requires a color", args[0])));
+        }
+        check_color(&rules, color.as_str())?;
+        Ok(color)
+    };
+    match args.first().map(String::as_str) {
+        Some("containers-of") => {
+            let mut containers = containers_of(&rules, color()?.as_str(), depth)
+                .into_iter()
+                .collect::<Vec<_>>();
+            containers.sort();
+            for c in containers.iter() {
+                println!("{}", c);
+            }
+            println!("{} bag colors", containers.len());
+        }
+        Some("contents-of") => println!("{}", contents_of(&rules, color()?.as_str(), depth)?),
+        Some("export") => {
+            let graph = subgraph(&rules, from.as_deref(), into.as_deref());
+            match args.get(1).map(String::as_str) {
+                Some("dot") => println!("{}", render_dot(&graph)),
+                Some("json") => println!("{}", render_json(&graph)?),
+                _ => return Err(SimpleError::new("export requires a format, dot or json").into()),
+            }
+        }
+        Some(c) => {
+            return Err(SimpleError::new(format!(
+                "unknown command {}, expected containers-of <color>, contents-of <color> or export <dot|json>",
+                c
+            ))
+            .into())
+        }
+        None => {
+            println!("part 1: {}", part1(&rules));
+            println!("part 2: {}", part2(&rules)?);
+        }
+    }
 
     Ok(())
 }
@@ -1,11 +1,13 @@
 use crate::Instruction::{Acc, Jmp, Nop};
 use simple_error::SimpleError;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::ops::Index;
+use std::str::FromStr;
 
 type Arch = i32;
 
@@ -16,6 +18,74 @@ enum Instruction {
     Nop(Arch),
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum Opcode {
+    Acc,
+    Jmp,
+    Nop,
+}
+
+impl FromStr for Opcode {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "acc" => Ok(Opcode::Acc),
+            "jmp" => Ok(Opcode::Jmp),
+            "nop" => Ok(Opcode::Nop),
+            s => Err(SimpleError::new(format!("unrecognized instruction: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Opcode::Acc => "acc",
+            Opcode::Jmp => "jmp",
+            Opcode::Nop => "nop",
+        })
+    }
+}
+
+impl Instruction {
+    fn opcode(self) -> Opcode {
+        match self {
+            Acc(_) => Opcode::Acc,
+            Jmp(_) => Opcode::Jmp,
+            Nop(_) => Opcode::Nop,
+        }
+    }
+
+    fn operand(self) -> Arch {
+        match self {
+            Acc(v) | Jmp(v) | Nop(v) => v,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Box<dyn Error>;
+
+    fn from_str(l: &str) -> Result<Self, Self::Err> {
+        let i = l
+            .find(' ')
+            .ok_or_else(|| SimpleError::new(format!("error parsing instruction: {}", l)))?;
+        let v = l[(i + 1)..].parse()?;
+        Ok(match l[..i].parse()? {
+            Opcode::Acc => Acc(v),
+            Opcode::Jmp => Jmp(v),
+            Opcode::Nop => Nop(v),
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:+}", self.opcode(), self.operand())
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 struct Memory {
     ram: Vec<Instruction>,
@@ -39,22 +109,7 @@ impl<R: BufRead> TryFrom<Wrapper<R>> for Memory {
             ram: reader
                 .0
                 .lines()
-                .map::<Result<Instruction, Box<dyn Error>>, _>(|r| {
-                    let l = r?;
-                    let i = l.find(" ").map(Ok).unwrap_or_else(|| {
-                        Err(SimpleError::new(format!(
-                            "error parsing instruction: {}",
-                            l
-                        )))
-                    })?;
-                    let v = l[(i + 1)..].parse()?;
-                    match &l[..i] {
-                        "acc" => Ok(Acc(v)),
-                        "jmp" => Ok(Jmp(v)),
-                        "nop" => Ok(Nop(v)),
-                        s => Err(SimpleError::new(format!("unrecognized instruction: {}", s)))?,
-                    }
-                })
+                .map::<Result<Instruction, Box<dyn Error>>, _>(|r| r?.parse())
                 .collect::<Result<Vec<Instruction>, Box<dyn Error>>>()?,
         })
     }
@@ -75,6 +130,17 @@ impl Cpu {
         }
         self.pc += 1;
     }
+
+    fn checked_execute(&mut self, instruction: Instruction) -> Option<()> {
+        let (acc, pc) = match instruction {
+            Acc(v) => (self.acc.checked_add(v)?, self.pc.checked_add(1)?),
+            Jmp(o) => (self.acc, self.pc.checked_add(o)?),
+            Nop(_) => (self.acc, self.pc.checked_add(1)?),
+        };
+        self.acc = acc;
+        self.pc = pc;
+        Some(())
+    }
 }
 
 #[derive(Debug, Default)]
@@ -84,6 +150,17 @@ struct Console {
 }
 
 impl Console {
+    fn fetch(&self) -> Option<Instruction> {
+        usize::try_from(self.cpu.pc)
+            .ok()
+            .and_then(|pc| self.mem.ram.get(pc))
+            .copied()
+    }
+
+    fn terminated(&self) -> bool {
+        self.cpu.pc == self.mem.ram.len() as i32
+    }
+
     fn step(&mut self) -> bool {
         let i = self.mem[self.cpu.pc];
         self.cpu.execute(i);
@@ -100,6 +177,283 @@ impl Console {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum Breakpoint {
+    Address(Arch),
+    Opcode(Opcode),
+}
+
+impl FromStr for Breakpoint {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(pc) => Ok(Breakpoint::Address(pc)),
+            Err(_) => s.parse().map(Breakpoint::Opcode).map_err(|_| {
+                SimpleError::new(format!(
+                    "invalid breakpoint {}, expected an address, acc, jmp or nop",
+                    s
+                ))
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Address(pc) => write!(f, "pc {}", pc),
+            Breakpoint::Opcode(op) => write!(f, "every {}", op),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Watch {
+    Change,
+    Equals(Arch),
+}
+
+enum Stop {
+    Stepped,
+    Terminated,
+    Reached(Arch),
+    Breakpoint(Breakpoint),
+    Watch(Arch, Arch),
+    Loop(Arch),
+    Fault(Arch),
+    Overflow(Arch, Instruction),
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Stepped => Ok(()),
+            Stop::Terminated => write!(f, "program terminated"),
+            Stop::Reached(pc) => write!(f, "reached pc {}", pc),
+            Stop::Breakpoint(b) => write!(f, "breakpoint: {}", b),
+            Stop::Watch(old, new) => write!(f, "watch: acc {} -> {}", old, new),
+            Stop::Loop(pc) => write!(f, "loop detected: pc {} is about to run again", pc),
+            Stop::Fault(pc) => write!(f, "fault: pc {} is outside the program", pc),
+            Stop::Overflow(pc, i) => write!(f, "fault: {} at pc {} overflows", i, pc),
+        }
+    }
+}
+
+struct Debugger {
+    console: Console,
+    original: Memory,
+    breakpoints: BTreeSet<Breakpoint>,
+    watch: Option<Watch>,
+}
+
+impl Debugger {
+    fn new(mem: Memory) -> Self {
+        let mut console = Console::default();
+        console.load(mem.clone());
+        Debugger {
+            console,
+            original: mem,
+            breakpoints: BTreeSet::new(),
+            watch: None,
+        }
+    }
+
+    fn breakpoint_at(&self, instruction: Instruction) -> Option<Breakpoint> {
+        self.breakpoints.iter().copied().find(|b| match b {
+            Breakpoint::Address(pc) => *pc == self.console.cpu.pc,
+            Breakpoint::Opcode(op) => *op == instruction.opcode(),
+        })
+    }
+
+    fn run(&mut self, limit: Option<usize>, until: Option<Arch>) -> Stop {
+        let mut visited = HashSet::new();
+        let mut steps = 0;
+        loop {
+            if self.console.terminated() {
+                return Stop::Terminated;
+            }
+            let pc = self.console.cpu.pc;
+            let instruction = match self.console.fetch() {
+                Some(i) => i,
+                None => return Stop::Fault(pc),
+            };
+            if steps > 0 {
+                if until == Some(pc) {
+                    return Stop::Reached(pc);
+                }
+                if let Some(b) = self.breakpoint_at(instruction) {
+                    return Stop::Breakpoint(b);
+                }
+            }
+            if limit == Some(steps) {
+                return Stop::Stepped;
+            }
+            if !visited.insert(pc) && limit.is_none() {
+                return Stop::Loop(pc);
+            }
+
+            let before = self.console.cpu.acc;
+            if self.console.cpu.checked_execute(instruction).is_none() {
+                return Stop::Overflow(pc, instruction);
+            }
+            steps += 1;
+            let after = self.console.cpu.acc;
+            match self.watch {
+                Some(Watch::Change) if before != after => return Stop::Watch(before, after),
+                Some(Watch::Equals(v)) if before != after && after == v => {
+                    return Stop::Watch(before, after)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn address(&self, arg: Option<&str>) -> Result<Arch, Box<dyn Error>> {
+        let pc = arg
+            .ok_or_else(|| SimpleError::new("expected an address"))?
+            .parse()?;
+        if usize::try_from(pc).map_or(true, |pc| pc >= self.console.mem.ram.len()) {
+            return Err(SimpleError::new(format!("address {} is outside the program", pc)).into());
+        }
+        Ok(pc)
+    }
+
+    fn print_state(&self) {
+        let cpu = &self.console.cpu;
+        match self.console.fetch() {
+            Some(i) => println!("pc {}: {}  (acc {})", cpu.pc, i, cpu.acc),
+            None => println!("pc {}  (acc {})", cpu.pc, cpu.acc),
+        }
+    }
+
+    fn print_listing(&self, context: Arch) {
+        let pc = self.console.cpu.pc;
+        let len = self.console.mem.ram.len() as Arch;
+        let start = pc.saturating_sub(context.max(0)).max(0);
+        let end = pc.saturating_add(context.max(0)).saturating_add(1).min(len);
+        for addr in start..end {
+            let instruction = self.console.mem[addr];
+            let marker = if addr == pc { "=>" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&Breakpoint::Address(addr))
+                || self
+                    .breakpoints
+                    .contains(&Breakpoint::Opcode(instruction.opcode()))
+            {
+                "*"
+            } else {
+                " "
+            };
+            println!("{}{} {:>5}: {}", marker, breakpoint, addr, instruction);
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<bool, Box<dyn Error>> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(c) => c,
+            None => return Ok(true),
+        };
+        let args = words.collect::<Vec<_>>();
+        let arg = args.first().copied();
+        match command {
+            "s" | "step" => {
+                let n = arg.map(str::parse).transpose()?.unwrap_or(1);
+                self.resume(Some(n), None);
+            }
+            "c" | "continue" => self.resume(None, None),
+            "u" | "until" => {
+                let pc = self.address(arg)?;
+                self.resume(None, Some(pc));
+            }
+            "b" | "break" => {
+                let b = arg
+                    .ok_or_else(|| SimpleError::new("expected an address or instruction kind"))?
+                    .parse::<Breakpoint>()?;
+                if let Breakpoint::Address(pc) = b {
+                    self.address(Some(pc.to_string().as_str()))?;
+                }
+                self.breakpoints.insert(b);
+                println!("breakpoint set: {}", b);
+            }
+            "d" | "delete" => match arg {
+                Some(a) => {
+                    let b = a.parse::<Breakpoint>()?;
+                    if !self.breakpoints.remove(&b) {
+                        return Err(SimpleError::new(format!("no breakpoint on {}", b)).into());
+                    }
+                }
+                None => self.breakpoints.clear(),
+            },
+            "breakpoints" => {
+                for b in self.breakpoints.iter() {
+                    println!("{}", b);
+                }
+            }
+            "w" | "watch" => {
+                self.watch = Some(match arg {
+                    Some(v) => Watch::Equals(v.parse()?),
+                    None => Watch::Change,
+                });
+            }
+            "unwatch" => self.watch = None,
+            "l" | "list" => self.print_listing(arg.map(str::parse).transpose()?.unwrap_or(3)),
+            "p" | "print" => self.print_state(),
+            "patch" => {
+                let pc = self.address(arg)?;
+                let instruction = args[1..].join(" ").parse::<Instruction>()?;
+                let old = std::mem::replace(&mut self.console.mem.ram[pc as usize], instruction);
+                println!("pc {}: {} -> {}", pc, old, instruction);
+            }
+            "reset" => {
+                self.console.cpu = Cpu::default();
+                self.print_state();
+            }
+            "reload" => {
+                self.console.reset();
+                self.console.load(self.original.clone());
+                self.print_state();
+            }
+            "h" | "help" => println!(
+                "step [n], continue, until <pc>, break <pc|acc|jmp|nop>, delete [bp], \
+                 breakpoints, watch [value], unwatch, list [n], print, patch <pc> <instruction>, \
+                 reset, reload, quit"
+            ),
+            "q" | "quit" => return Ok(false),
+            c => return Err(SimpleError::new(format!("unknown command {}, try help", c)).into()),
+        }
+        Ok(true)
+    }
+
+    fn resume(&mut self, limit: Option<usize>, until: Option<Arch>) {
+        let stop = self.run(limit, until);
+        if !matches!(stop, Stop::Stepped) {
+            println!("{}", stop);
+        }
+        self.print_state();
+    }
+}
+
+fn debug(mem: Memory) -> Result<(), Box<dyn Error>> {
+    let mut debugger = Debugger::new(mem);
+    debugger.print_state();
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("(day8) ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match debugger.execute(line.trim()) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+}
+
 fn part1(console: &mut Console) -> Arch {
     let mut visited = HashSet::new();
     while !visited.contains(&console.cpu.pc) {
@@ -152,6 +506,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut console = Console::default();
 
     let mem: Memory = Wrapper(BufReader::new(File::open("input.txt")?)).try_into()?;
+    if std::env::args().nth(1).as_deref() == Some("debug") {
+        return debug(mem);
+    }
     console.load(mem.clone());
 
     println!("part 1: {}", part1(&mut console));